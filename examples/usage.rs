@@ -1,8 +1,5 @@
 use anyhow::Result;
-use carla::{
-    client::{ActorBase, Client, Vehicle},
-    rpc::VehicleControl,
-};
+use carla::client::{Client, Vehicle};
 use carla_ackermann::{TargetRequest, VehicleController};
 use clap::Parser;
 use rand::prelude::*;
@@ -45,39 +42,14 @@ fn main() -> Result<()> {
         accel: 1.0,
     });
 
-    // Get initial world ID and elapsed simulation time.
-    let snapshot = world.wait_for_tick();
-    let mut world_id = snapshot.id();
-    let mut time_secs = snapshot.timestamp().elapsed_seconds;
+    // Get the initial world snapshot.
+    let mut prev_snapshot = world.wait_for_tick();
 
     loop {
-        // Compute time delta since last step.
-        // If the world is reloaded, the time delta is set to zero.
-        let curr_id = snapshot.id();
-        let curr_secs = snapshot.timestamp().elapsed_seconds;
-
-        let time_delta_secs = if curr_id != world_id {
-            0.0
-        } else {
-            curr_secs - time_secs
-        };
-        world_id = curr_id;
-        time_secs = curr_secs;
-
-        // Generate a control command from the controller
-        let speed = vehicle.velocity().norm();
-        let (_, pitch, _) = vehicle.transform().rotation.euler_angles();
-        let (output, _report) = controller.step(time_delta_secs, speed as f64, pitch as f64);
-
-        // Apply control to the car
-        vehicle.apply_control(&VehicleControl {
-            throttle: output.throttle as f32,
-            steer: output.steer as f32,
-            brake: output.brake as f32,
-            hand_brake: output.hand_brake,
-            reverse: output.reverse,
-            manual_gear_shift: false,
-            gear: 0,
-        });
+        // Step the controller and apply the control to the car. The
+        // step is skipped if the world is reloaded.
+        let snapshot = world.wait_for_tick();
+        let _report = controller.tick(&mut vehicle, &snapshot, &prev_snapshot);
+        prev_snapshot = snapshot;
     }
 }
@@ -1,10 +1,22 @@
 use crate::{physics::VehiclePhysics, pid::PidInit};
-use pid::Pid;
+use pid::{ControlOutput, Pid};
 
 #[derive(Debug, Clone)]
 pub struct AccelControllerInit {
     pub pid: PidInit,
     pub max_pedal: f64,
+    /// The brake command in [0, 1], below which the brakes produce no
+    /// deceleration. Any nonzero brake starts from this offset.
+    pub brake_intercept: f64,
+    /// The target pedal at construction.
+    ///
+    /// Zero is the equilibrium of the physics model at any speed,
+    /// since the pedal borders already cancel the driving resistance.
+    /// Set it, e.g. with
+    /// [target_pedal_for_throttle](crate::VehicleControllerInit::target_pedal_for_throttle),
+    /// when the plant deviates from the model to avoid a dip at
+    /// engagement.
+    pub initial_target_pedal: f64,
 }
 
 impl AccelControllerInit {
@@ -15,28 +27,42 @@ impl AccelControllerInit {
                 ki: 0.0,
                 kd: 0.05,
                 output_limit: 1.0,
+                integral_limit: 1.0,
             },
             max_pedal: physics.max_accel().min(physics.max_deceleration()),
+            brake_intercept: 0.0,
+            initial_target_pedal: 0.0,
         }
     }
 
     pub fn build(&self) -> AccelController {
-        let Self { ref pid, max_pedal } = *self;
+        let Self {
+            ref pid,
+            max_pedal,
+            brake_intercept,
+            initial_target_pedal,
+        } = *self;
         AccelController {
             accel_pid: pid.build(),
+            accel_integral: 0.0,
+            integral_gain: pid.ki,
             target_accel: 0.0,
-            target_pedal: 0.0,
+            target_pedal: initial_target_pedal.clamp(-max_pedal, max_pedal),
             max_pedal,
+            brake_intercept,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AccelController {
     accel_pid: Pid<f64>,
+    accel_integral: f64,
+    integral_gain: f64,
     target_accel: f64,
     target_pedal: f64,
     max_pedal: f64,
+    brake_intercept: f64,
 }
 
 impl AccelController {
@@ -48,31 +74,157 @@ impl AccelController {
         self.target_pedal = 0.0;
     }
 
+    /// Overrides the integral gain of the PID while `ki` is `Some`.
+    ///
+    /// When set back to `None`, the configured integral gain is
+    /// restored and the integral term is cleared.
+    pub fn set_integral_override(&mut self, ki: Option<f64>) {
+        match ki {
+            Some(ki) => self.accel_pid.ki = ki,
+            None => {
+                if self.accel_pid.ki != self.integral_gain {
+                    self.accel_pid.ki = self.integral_gain;
+                    self.accel_pid.reset_integral_term();
+                    self.accel_integral = 0.0;
+                }
+            }
+        }
+    }
+
+    /// Multiplies the proportional, integral and derivative gains of
+    /// the acceleration PID by `factor`, including the configured
+    /// integral gain restored after an override.
+    pub fn scale_gains(&mut self, factor: f64) {
+        let pid = &mut self.accel_pid;
+        pid.kp *= factor;
+        pid.ki *= factor;
+        pid.kd *= factor;
+        self.integral_gain *= factor;
+    }
+
     pub fn step(&mut self, current_accel: f64) -> AccelControl {
         let Self {
             ref mut accel_pid,
             target_pedal: prev_target_pedal,
             max_pedal,
             target_accel,
+            ..
         } = *self;
 
         accel_pid.setpoint = target_accel;
-        let pedal_delta = accel_pid.next_control_output(current_accel).output;
+        let pid_output = accel_pid.next_control_output(current_accel);
+        self.accel_integral = pid_output.i;
+        let pedal_delta = pid_output.output;
         let curr_pedal_target = (prev_target_pedal + pedal_delta).clamp(-max_pedal, max_pedal);
         self.target_pedal = curr_pedal_target;
 
         AccelControl {
             target_pedal: curr_pedal_target,
             pedal_delta,
+            pid_output: Some(pid_output),
         }
     }
 
+    /// Keeps the current target pedal without running the PID.
+    pub fn hold(&self) -> AccelControl {
+        AccelControl {
+            target_pedal: self.target_pedal,
+            pedal_delta: 0.0,
+            pid_output: None,
+        }
+    }
+
+    /// Gets the integral term of the acceleration PID as of its last
+    /// run.
+    pub fn integral(&self) -> f64 {
+        self.accel_integral
+    }
+
+    pub fn target_accel(&self) -> f64 {
+        self.target_accel
+    }
+
     pub fn max_pedal(&self) -> f64 {
         self.max_pedal
     }
+
+    pub fn brake_intercept(&self) -> f64 {
+        self.brake_intercept
+    }
+
+    /// Maps a linear brake ratio in [0, 1] to the brake command,
+    /// accounting for the brake intercept.
+    pub fn brake_command(&self, brake_ratio: f64) -> f64 {
+        if brake_ratio > 0.0 {
+            let intercept = self.brake_intercept;
+            intercept + (1.0 - intercept) * brake_ratio
+        } else {
+            0.0
+        }
+    }
 }
 
 pub struct AccelControl {
     pub target_pedal: f64,
     pub pedal_delta: f64,
+    /// The raw acceleration PID output if the PID ran in this step.
+    pub pid_output: Option<ControlOutput<f64>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::VehicleClass;
+
+    fn controller(ki: f64) -> AccelController {
+        let mut init =
+            AccelControllerInit::from_physics(&VehiclePhysics::preset(VehicleClass::Sedan));
+        init.pid.ki = ki;
+        init.build()
+    }
+
+    #[test]
+    fn initial_target_pedal_is_clamped() {
+        let mut init =
+            AccelControllerInit::from_physics(&VehiclePhysics::preset(VehicleClass::Sedan));
+        init.initial_target_pedal = 10.0;
+        let controller = init.build();
+        assert_eq!(controller.hold().target_pedal, controller.max_pedal());
+    }
+
+    #[test]
+    fn integral_override_restores_configured_gain() {
+        let mut controller = controller(0.1);
+        controller.set_target_accel(1.0);
+        controller.set_integral_override(Some(0.5));
+        controller.step(0.0);
+        assert_eq!(controller.integral(), 0.5);
+
+        controller.set_integral_override(None);
+        assert_eq!(controller.integral(), 0.0);
+        controller.step(0.0);
+        assert!((controller.integral() - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn scale_gains_scales_restored_integral_gain() {
+        let mut controller = controller(0.1);
+        controller.scale_gains(2.0);
+        controller.set_integral_override(Some(1.0));
+        controller.set_integral_override(None);
+        controller.set_target_accel(1.0);
+        controller.step(0.0);
+        assert!((controller.integral() - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn brake_command_starts_at_intercept() {
+        let mut init =
+            AccelControllerInit::from_physics(&VehiclePhysics::preset(VehicleClass::Sedan));
+        init.brake_intercept = 0.2;
+        let controller = init.build();
+        assert_eq!(controller.brake_command(0.0), 0.0);
+        assert!((controller.brake_command(0.5) - 0.6).abs() < 1e-12);
+        assert_eq!(controller.brake_command(1.0), 1.0);
+    }
 }
@@ -0,0 +1,230 @@
+use crate::{speed_control::AccelMode, Output, TargetRequest, VehicleController};
+
+/// Parameters of [characterize].
+#[derive(Debug, Clone)]
+pub struct SweepConfig {
+    /// The fixed pedals to apply in order, each in [-1, 1].
+    pub pedals: Vec<f64>,
+    /// The time step in seconds passed to the plant.
+    pub time_delta_sec: f64,
+    /// The maximum number of steps to wait for each pedal.
+    pub max_steps: usize,
+    /// The speed is considered steady once it changes less than this
+    /// value (m/s) within one step.
+    pub steady_speed_delta: f64,
+    /// The number of consecutive steady steps required.
+    pub steady_steps: usize,
+}
+
+impl SweepConfig {
+    /// Creates a sweep over `num_pedals` throttle values evenly spaced
+    /// in (0, 1].
+    pub fn throttle_sweep(num_pedals: usize) -> Self {
+        Self {
+            pedals: (1..=num_pedals)
+                .map(|index| index as f64 / num_pedals as f64)
+                .collect(),
+            time_delta_sec: 0.05,
+            max_steps: 20_000,
+            steady_speed_delta: 1e-4,
+            steady_steps: 20,
+        }
+    }
+}
+
+/// Maps each fixed pedal to the steady-state speed it results in.
+///
+/// The `plant` applies the given pedal for the given seconds and
+/// returns the resulting speed. It can wrap a simulated model or a
+/// real speed source. The plant keeps its state across pedals, so the
+/// sweep continues from the last steady speed.
+///
+/// Returns `(pedal, steady_speed)` pairs in the order of
+/// [SweepConfig::pedals]. If the speed does not settle within
+/// `max_steps`, the last measured speed is reported.
+pub fn characterize<F>(config: &SweepConfig, mut plant: F) -> Vec<(f64, f64)>
+where
+    F: FnMut(f64, f64) -> f64,
+{
+    let SweepConfig {
+        ref pedals,
+        time_delta_sec,
+        max_steps,
+        steady_speed_delta,
+        steady_steps,
+    } = *config;
+
+    pedals
+        .iter()
+        .map(|&pedal| {
+            let mut prev_speed = None;
+            let mut steady_count = 0;
+            let mut speed = 0.0;
+
+            for _ in 0..max_steps {
+                speed = plant(pedal, time_delta_sec);
+
+                let is_steady = prev_speed
+                    .map(|prev: f64| (speed - prev).abs() < steady_speed_delta)
+                    .unwrap_or(false);
+                steady_count = if is_steady { steady_count + 1 } else { 0 };
+                prev_speed = Some(speed);
+
+                if steady_count >= steady_steps {
+                    break;
+                }
+            }
+
+            (pedal, speed)
+        })
+        .collect()
+}
+
+/// Parameters of [characterize_accel].
+#[derive(Debug, Clone)]
+pub struct AccelSweepConfig {
+    /// The constant accel (m/s²) to hold. It can be negative for a
+    /// deceleration sweep.
+    pub target_accel: f64,
+    /// The speed (m/s) the sweep starts from.
+    pub initial_speed: f64,
+    /// The speed (m/s) that ends the sweep once reached.
+    pub final_speed: f64,
+    /// The time step in seconds passed to the controller and the plant.
+    pub time_delta_sec: f64,
+    /// The maximum number of steps of the sweep.
+    pub max_steps: usize,
+    /// The number of initial steps not recorded while the accel PID
+    /// converges.
+    pub settle_steps: usize,
+}
+
+impl AccelSweepConfig {
+    /// Creates a sweep holding `target_accel` from standstill up to
+    /// `final_speed`.
+    pub fn new(target_accel: f64, final_speed: f64) -> Self {
+        Self {
+            target_accel,
+            initial_speed: 0.0,
+            final_speed,
+            time_delta_sec: 0.05,
+            max_steps: 20_000,
+            settle_steps: 20,
+        }
+    }
+}
+
+/// Holds a constant accel with the accel PID of `controller` and maps
+/// the speed to the pedal it converges to, e.g. to build pedal maps.
+///
+/// The controller is switched to [AccelMode::Setpoint] and targets
+/// [AccelSweepConfig::final_speed]. The `plant` applies the given
+/// output for the given seconds on flat ground and returns the
+/// resulting speed.
+///
+/// Returns `(speed, pedal)` pairs, one per recorded step, where the
+/// pedal is [Output::as_unified_pedal].
+pub fn characterize_accel<F>(
+    controller: &mut VehicleController,
+    config: &AccelSweepConfig,
+    mut plant: F,
+) -> Vec<(f64, f64)>
+where
+    F: FnMut(&Output, f64) -> f64,
+{
+    let AccelSweepConfig {
+        target_accel,
+        initial_speed,
+        final_speed,
+        time_delta_sec,
+        max_steps,
+        settle_steps,
+    } = *config;
+
+    controller.set_accel_mode(AccelMode::Setpoint);
+    controller.set_target(TargetRequest {
+        steering_angle: 0.0,
+        speed: final_speed,
+        accel: target_accel,
+    });
+
+    let mut samples = vec![];
+    let mut speed = initial_speed;
+
+    for index in 0..max_steps {
+        let is_finished = if target_accel >= 0.0 {
+            speed >= final_speed
+        } else {
+            speed <= final_speed
+        };
+        if is_finished {
+            break;
+        }
+
+        let (output, _) = controller.step(time_delta_sec, speed, 0.0);
+        if index >= settle_steps {
+            samples.push((speed, output.as_unified_pedal()));
+        }
+        speed = plant(&output, time_delta_sec);
+    }
+
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        physics::{VehicleClass, VehiclePhysics},
+        VehicleControllerInit,
+    };
+
+    #[test]
+    fn characterize_reports_steady_speeds() {
+        let config = SweepConfig::throttle_sweep(4);
+        assert_eq!(config.pedals, [0.25, 0.5, 0.75, 1.0]);
+
+        // A first-order plant settling at 10 m/s per unit pedal
+        let mut speed = 0.0;
+        let samples = characterize(&config, |pedal, time_delta_sec| {
+            speed += (2.0 * pedal - 0.2 * speed) * time_delta_sec;
+            speed
+        });
+        for (pedal, steady_speed) in samples {
+            assert!((steady_speed - 10.0 * pedal).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn characterize_accel_sweeps_up_to_final_speed() {
+        let physics = VehiclePhysics::preset(VehicleClass::Sedan);
+        let mut controller = VehicleControllerInit::from_physics(physics.clone(), None).build();
+        let config = AccelSweepConfig::new(1.0, 5.0);
+
+        // A point-mass plant with the drag of the physics model
+        let mut speed = config.initial_speed;
+        let samples = characterize_accel(&mut controller, &config, |output, time_delta_sec| {
+            let drag = physics
+                .driving_impedance_acceleration(speed, 0.0, false)
+                .abs();
+            speed += (3.0 * output.throttle - 8.0 * output.brake - drag) * time_delta_sec;
+            speed.max(0.0)
+        });
+        assert!(!samples.is_empty());
+        assert!(samples.iter().all(|&(speed, _)| speed < 5.0));
+
+        // The measured accel settles at the target
+        let time_delta_sec = config.time_delta_sec;
+        let half = samples.len() / 2;
+        for pair in samples[half..].windows(2) {
+            let accel = (pair[1].0 - pair[0].0) / time_delta_sec;
+            assert!((accel - 1.0).abs() < 0.1, "accel {accel}");
+        }
+
+        // The pedal rises with the drag
+        assert!(samples[half..]
+            .windows(2)
+            .all(|pair| pair[1].1 >= pair[0].1 - 1e-9));
+        assert!(samples.last().unwrap().1 > samples[half].1);
+    }
+}
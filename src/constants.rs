@@ -1,4 +1,37 @@
+/// The speed (m/s) below which the vehicle is considered standing still.
 pub const STAND_STILL_SPEED_MS: f64 = 0.1;
+/// The speed (m/s) below which a speed is considered a full stop.
 pub const FULL_STOP_SPEED_MS: f64 = 0.00001;
+/// The accel (m/s²) below which a target accel is considered inertial,
+/// that is, no accel constraint is given.
 pub const INTERNAL_ACCEL_MS2: f64 = 0.00001;
+/// The speed (m/s) below which the slip ratio is computed against this
+/// speed instead of the vehicle speed.
+pub const MIN_SLIP_REFERENCE_SPEED_MS: f64 = 0.5;
+/// The max steering angle (degrees) used by
+/// [VehiclePhysics::new](crate::physics::VehiclePhysics::new) when the
+/// vehicle has no wheels.
 pub const DEFAULT_MAX_STEERING_DEGREES: f64 = 70.0;
+
+/// The speed and accel thresholds used by the controllers.
+///
+/// The [Default] value is built from the constants in this module.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// See [STAND_STILL_SPEED_MS].
+    pub stand_still_speed: f64,
+    /// See [FULL_STOP_SPEED_MS].
+    pub full_stop_speed: f64,
+    /// See [INTERNAL_ACCEL_MS2].
+    pub inertial_accel: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            stand_still_speed: STAND_STILL_SPEED_MS,
+            full_stop_speed: FULL_STOP_SPEED_MS,
+            inertial_accel: INTERNAL_ACCEL_MS2,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+/// Accumulates the control effort over time for comparing tunings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ControlEffort {
+    /// The integral of `|throttle| + |brake|` over time in seconds.
+    pub effort: f64,
+    /// The number of switches between throttle and brake, ignoring
+    /// coasting in between.
+    pub reversals: usize,
+    /// The accumulated time in seconds.
+    pub duration_sec: f64,
+    last_throttle: Option<bool>,
+}
+
+impl ControlEffort {
+    /// Records the pedals applied for `time_delta_sec`.
+    pub fn push(&mut self, time_delta_sec: f64, throttle: f64, brake: f64) {
+        self.effort += (throttle.abs() + brake.abs()) * time_delta_sec;
+        self.duration_sec += time_delta_sec;
+
+        let is_throttle = if throttle > 0.0 {
+            Some(true)
+        } else if brake > 0.0 {
+            Some(false)
+        } else {
+            None
+        };
+        if let Some(is_throttle) = is_throttle {
+            if self.last_throttle.is_some_and(|last| last != is_throttle) {
+                self.reversals += 1;
+            }
+            self.last_throttle = Some(is_throttle);
+        }
+    }
+
+    /// Gets the effort per second, or zero if nothing is recorded.
+    pub fn mean_effort(&self) -> f64 {
+        if self.duration_sec > 0.0 {
+            self.effort / self.duration_sec
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The extremes seen by a controller, see
+/// [VehicleController::peaks](crate::VehicleController::peaks).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Peaks {
+    /// The highest measured accel in m/s², zero if none was positive.
+    pub max_accel: f64,
+    /// The highest measured deceleration in m/s² as a positive value.
+    pub max_decel: f64,
+    /// The highest measured speed in m/s.
+    pub max_speed: f64,
+    /// The highest magnitude of [Output::steer](crate::Output::steer).
+    pub max_steer: f64,
+}
+
+impl Peaks {
+    /// Records the measured accel and speed and the steer of a step.
+    pub fn push(&mut self, accel: f64, speed: f64, steer: f64) {
+        self.max_accel = self.max_accel.max(accel);
+        self.max_decel = self.max_decel.max(-accel);
+        self.max_speed = self.max_speed.max(speed.abs());
+        self.max_steer = self.max_steer.max(steer.abs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reversals_ignore_coasting() {
+        let mut effort = ControlEffort::default();
+        effort.push(0.5, 0.4, 0.0);
+        effort.push(0.5, 0.0, 0.0);
+        effort.push(0.5, 0.0, 0.2);
+        effort.push(0.5, 0.0, 0.2);
+        assert_eq!(effort.reversals, 1);
+        assert!((effort.effort - 0.4).abs() < 1e-12);
+        assert!((effort.mean_effort() - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn mean_effort_is_zero_without_samples() {
+        assert_eq!(ControlEffort::default().mean_effort(), 0.0);
+    }
+
+    #[test]
+    fn peaks_keep_magnitudes() {
+        let mut peaks = Peaks::default();
+        peaks.push(1.5, -3.0, -0.4);
+        peaks.push(-2.0, 2.0, 0.1);
+        assert_eq!(
+            peaks,
+            Peaks {
+                max_accel: 1.5,
+                max_decel: 2.0,
+                max_speed: 3.0,
+                max_steer: 0.4,
+            }
+        );
+    }
+}
@@ -0,0 +1,161 @@
+/// A first-order low-pass filter parameterized by a time constant.
+#[derive(Debug, Clone)]
+pub struct LowPassFilter {
+    time_constant: f64,
+    value: Option<f64>,
+}
+
+impl LowPassFilter {
+    pub fn new(time_constant: f64) -> Self {
+        Self {
+            time_constant,
+            value: None,
+        }
+    }
+
+    /// Feeds a sample taken `time_delta_sec` after the previous one
+    /// and returns the filtered value.
+    ///
+    /// The first sample initializes the filter output.
+    pub fn update(&mut self, input: f64, time_delta_sec: f64) -> f64 {
+        let next = match self.value {
+            Some(prev) => {
+                let alpha = time_delta_sec / (self.time_constant + time_delta_sec);
+                prev + alpha * (input - prev)
+            }
+            None => input,
+        };
+        self.value = Some(next);
+        next
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    pub fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+/// Limits the rate of change of a signal, starting from zero.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    max_rate: f64,
+    value: f64,
+}
+
+impl RateLimiter {
+    pub fn new(max_rate: f64) -> Self {
+        Self {
+            max_rate,
+            value: 0.0,
+        }
+    }
+
+    /// Moves the output toward `input` by at most
+    /// `max_rate * time_delta_sec` and returns it.
+    pub fn update(&mut self, input: f64, time_delta_sec: f64) -> f64 {
+        let max_delta = self.max_rate * time_delta_sec;
+        self.value += (input - self.value).clamp(-max_delta, max_delta);
+        self.value
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Restarts the output at `value`.
+    pub fn reset_to(&mut self, value: f64) {
+        self.value = value;
+    }
+}
+
+/// Limits the second derivative (jerk) of a signal.
+///
+/// The rate is also bounded so that the signal can settle on the input
+/// without overshooting it.
+#[derive(Debug, Clone)]
+pub struct JerkLimiter {
+    max_jerk: f64,
+    value: f64,
+    rate: f64,
+}
+
+impl JerkLimiter {
+    pub fn new(max_jerk: f64) -> Self {
+        Self {
+            max_jerk,
+            value: 0.0,
+            rate: 0.0,
+        }
+    }
+
+    /// Moves the output toward `input` within the jerk limit and
+    /// returns it.
+    pub fn update(&mut self, input: f64, time_delta_sec: f64) -> f64 {
+        let Self {
+            max_jerk,
+            value,
+            rate,
+        } = *self;
+        let error = input - value;
+
+        // The fastest rate from which the output can still stop at
+        // the input
+        let stopping_rate = (2.0 * max_jerk * error.abs()).sqrt();
+        let desired_rate = (error / time_delta_sec).clamp(-stopping_rate, stopping_rate);
+
+        let max_rate_delta = max_jerk * time_delta_sec;
+        let rate = rate + (desired_rate - rate).clamp(-max_rate_delta, max_rate_delta);
+
+        self.rate = rate;
+        self.value = value + rate * time_delta_sec;
+        self.value
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn reset(&mut self) {
+        self.reset_to(0.0);
+    }
+
+    /// Restarts the output at `value` at rest.
+    pub fn reset_to(&mut self, value: f64) {
+        self.value = value;
+        self.rate = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_limits_the_rate() {
+        let mut limiter = RateLimiter::new(2.0);
+        assert_eq!(limiter.update(10.0, 0.5), 1.0);
+        assert_eq!(limiter.update(10.0, 0.5), 2.0);
+        assert_eq!(limiter.update(-10.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn jerk_limiter_restarts_at_value() {
+        let mut limiter = JerkLimiter::new(1.0);
+        limiter.update(1.0, 0.1);
+        limiter.reset_to(-1.0);
+        assert_eq!(limiter.value(), -1.0);
+        // It starts at rest, so the first step moves by jerk * dt²
+        assert!((limiter.update(1.0, 0.1) + 0.99).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rate_limiter_restarts_at_value() {
+        let mut limiter = RateLimiter::new(2.0);
+        limiter.reset_to(20.0);
+        assert_eq!(limiter.update(20.0, 0.5), 20.0);
+        assert_eq!(limiter.update(10.0, 0.5), 19.0);
+    }
+}
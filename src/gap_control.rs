@@ -0,0 +1,180 @@
+use crate::vehicle_control::{Output, Report, TargetRequest, VehicleController};
+
+/// Initializer of [GapController].
+#[derive(Debug, Clone)]
+pub struct GapControllerInit {
+    /// The desired time gap to the leader in seconds.
+    pub time_headway: f64,
+    /// The desired gap to the leader at standstill in meters.
+    pub standstill_gap: f64,
+    /// The gain converting the gap error (m) to a speed correction (m/s).
+    pub gap_gain: f64,
+    /// The target accel passed to the inner controller.
+    pub accel: f64,
+}
+
+impl GapControllerInit {
+    pub fn new(time_headway: f64) -> Self {
+        Self {
+            time_headway,
+            standstill_gap: 2.0,
+            gap_gain: 0.3,
+            accel: 0.0,
+        }
+    }
+
+    pub fn build(&self, controller: VehicleController) -> GapController {
+        let Self {
+            time_headway,
+            standstill_gap,
+            gap_gain,
+            accel,
+        } = *self;
+
+        GapController {
+            controller,
+            time_headway,
+            standstill_gap,
+            gap_gain,
+            accel,
+        }
+    }
+}
+
+/// A follower controller that holds a time gap to a leading vehicle
+/// by driving an inner [VehicleController].
+#[derive(Debug)]
+pub struct GapController {
+    controller: VehicleController,
+    time_headway: f64,
+    standstill_gap: f64,
+    gap_gain: f64,
+    accel: f64,
+}
+
+impl GapController {
+    /// Computes the gap the follower should keep at `current_speed`.
+    pub fn desired_gap(&self, current_speed: f64) -> f64 {
+        self.standstill_gap + self.time_headway * current_speed.max(0.0)
+    }
+
+    /// Computes the follower target from the leader's state.
+    ///
+    /// # Parameters
+    /// - `current_speed` is the current speed of the follower.
+    /// - `leader_speed` is the current speed of the leader.
+    /// - `gap` is the measured distance to the leader in meters.
+    /// - `steering_angle` is the steering angle passed through to the
+    ///   inner controller.
+    pub fn target(
+        &self,
+        current_speed: f64,
+        leader_speed: f64,
+        gap: f64,
+        steering_angle: f64,
+    ) -> TargetRequest {
+        let gap_error = gap - self.desired_gap(current_speed);
+        let speed = (leader_speed + self.gap_gain * gap_error).max(0.0);
+
+        TargetRequest {
+            steering_angle,
+            speed,
+            accel: self.accel,
+        }
+    }
+
+    /// Updates the follower target and steps the inner controller.
+    ///
+    /// See [VehicleController::step] and [GapController::target] for
+    /// the parameters.
+    pub fn step(
+        &mut self,
+        time_delta_sec: f64,
+        current_speed: f64,
+        pitch_radians: f64,
+        leader_speed: f64,
+        gap: f64,
+        steering_angle: f64,
+    ) -> (Output, Report) {
+        let target = self.target(current_speed, leader_speed, gap, steering_angle);
+        self.controller.set_target(target);
+        self.controller
+            .step(time_delta_sec, current_speed, pitch_radians)
+    }
+
+    pub fn controller(&self) -> &VehicleController {
+        &self.controller
+    }
+
+    pub fn controller_mut(&mut self) -> &mut VehicleController {
+        &mut self.controller
+    }
+
+    pub fn into_controller(self) -> VehicleController {
+        self.controller
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        physics::{VehicleClass, VehiclePhysics},
+        vehicle_control::VehicleControllerInit,
+    };
+
+    const TIME_DELTA_SEC: f64 = 0.05;
+
+    /// Advances a point-mass follower by one step under `output`.
+    fn plant_step(physics: &VehiclePhysics, output: &Output, velocity: f64) -> f64 {
+        let drive = output.throttle * physics.max_accel();
+        let resistance = physics
+            .driving_impedance_acceleration(velocity, 0.0, false)
+            .abs();
+        let decel = output.brake * physics.max_deceleration() + resistance;
+        (velocity + (drive - decel) * TIME_DELTA_SEC).max(0.0)
+    }
+
+    fn gap_controller() -> GapController {
+        let physics = VehiclePhysics::preset(VehicleClass::Sedan);
+        let controller = VehicleControllerInit::from_physics(physics, None).build();
+        GapControllerInit::new(1.5).build(controller)
+    }
+
+    #[test]
+    fn desired_gap_grows_with_speed() {
+        let controller = gap_controller();
+        assert_eq!(controller.desired_gap(0.0), 2.0);
+        assert_eq!(controller.desired_gap(-3.0), 2.0);
+        assert_eq!(controller.desired_gap(10.0), 17.0);
+    }
+
+    #[test]
+    fn target_speed_corrects_gap_error() {
+        let controller = gap_controller();
+        // Too close by 5 m at 10 m/s behind a 10 m/s leader
+        let target = controller.target(10.0, 10.0, 12.0, 0.1);
+        assert!((target.speed - 8.5).abs() < 1e-12);
+        assert_eq!(target.steering_angle, 0.1);
+        // Never asks to reverse
+        assert_eq!(controller.target(10.0, 0.0, 0.0, 0.0).speed, 0.0);
+    }
+
+    #[test]
+    fn gap_converges_behind_steady_leader() {
+        let physics = VehiclePhysics::preset(VehicleClass::Sedan);
+        let mut controller = gap_controller();
+        let leader_speed = 10.0;
+        let (mut velocity, mut gap) = (10.0, 30.0);
+
+        for _ in 0..2400 {
+            let (output, _) =
+                controller.step(TIME_DELTA_SEC, velocity, 0.0, leader_speed, gap, 0.0);
+            velocity = plant_step(&physics, &output, velocity);
+            gap += (leader_speed - velocity) * TIME_DELTA_SEC;
+        }
+        // The gap settles at standstill_gap + time_headway * speed
+        assert!((velocity - leader_speed).abs() < 0.1);
+        assert!((gap - controller.desired_gap(leader_speed)).abs() < 0.2);
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+
+const MIN_SIGNIFICANT_DELTA: f64 = 1e-6;
+
+/// Detects oscillation from the recent history of controller deltas.
+///
+/// The score is the fraction of sign changes between consecutive
+/// significant deltas in the window, ranging from 0 (monotonic) to 1
+/// (flipping on every step).
+#[derive(Debug, Clone)]
+pub struct InstabilityDetector {
+    window: usize,
+    threshold: f64,
+    signs: VecDeque<bool>,
+}
+
+impl InstabilityDetector {
+    pub fn new(window: usize, threshold: f64) -> Self {
+        Self {
+            window,
+            threshold,
+            signs: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Records a delta and returns the current score.
+    pub fn push(&mut self, delta: f64) -> f64 {
+        if self.window > 0 && delta.abs() >= MIN_SIGNIFICANT_DELTA {
+            if self.signs.len() == self.window {
+                self.signs.pop_front();
+            }
+            self.signs.push_back(delta.is_sign_positive());
+        }
+        self.score()
+    }
+
+    pub fn score(&self) -> f64 {
+        if self.signs.len() < 2 {
+            return 0.0;
+        }
+        let changes = self
+            .signs
+            .iter()
+            .zip(self.signs.iter().skip(1))
+            .filter(|(prev, next)| prev != next)
+            .count();
+        changes as f64 / (self.signs.len() - 1) as f64
+    }
+
+    /// Returns true if the window is full and the score reaches the
+    /// threshold.
+    pub fn is_unstable(&self) -> bool {
+        self.signs.len() == self.window && self.score() >= self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alternating_deltas_are_unstable() {
+        let mut detector = InstabilityDetector::new(4, 0.9);
+        for delta in [1.0, -1.0, 1.0] {
+            detector.push(delta);
+        }
+        assert!(!detector.is_unstable());
+        assert_eq!(detector.push(-1.0), 1.0);
+        assert!(detector.is_unstable());
+    }
+
+    #[test]
+    fn insignificant_deltas_are_skipped() {
+        let mut detector = InstabilityDetector::new(3, 0.5);
+        detector.push(1.0);
+        detector.push(-1e-9);
+        detector.push(2.0);
+        assert_eq!(detector.push(3.0), 0.0);
+        assert!(!detector.is_unstable());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::physics::VehiclePhysics;
+
+/// Initializer of [LateralController].
+#[derive(Debug, Clone)]
+pub struct LateralControllerInit {
+    /// The gain on the cross-track error of the Stanley control law.
+    pub cross_track_gain: f64,
+    /// The speed (m/s) added to the current speed in the cross-track
+    /// term, so that the steering stays bounded at low speed.
+    pub softening_speed: f64,
+    pub max_steering_angle: f64,
+}
+
+impl LateralControllerInit {
+    pub fn from_physics(physics: &VehiclePhysics) -> Self {
+        Self {
+            cross_track_gain: 2.5,
+            softening_speed: 1.0,
+            max_steering_angle: physics.max_steering_angle(),
+        }
+    }
+
+    pub fn build(&self) -> LateralController {
+        let Self {
+            cross_track_gain,
+            softening_speed,
+            max_steering_angle,
+        } = *self;
+
+        LateralController {
+            cross_track_gain,
+            softening_speed,
+            max_steering_angle,
+            steering_angle: 0.0,
+        }
+    }
+}
+
+/// A Stanley lateral controller producing the steering angle from
+/// the path tracking errors.
+///
+/// The steering angle follows the sign convention of
+/// [SteerController](crate::steer_control::SteerController) and can
+/// be passed as [TargetRequest::steering_angle](crate::TargetRequest).
+#[derive(Debug)]
+pub struct LateralController {
+    cross_track_gain: f64,
+    softening_speed: f64,
+    max_steering_angle: f64,
+    steering_angle: f64,
+}
+
+impl LateralController {
+    /// Updates the path errors and returns the new steering angle.
+    ///
+    /// # Parameters
+    /// - `cross_track` is the distance (m) of the front axle from the
+    ///   path, positive when the vehicle is left of the path.
+    /// - `heading_error` is the path heading relative to the vehicle
+    ///   heading in radians, positive when the path turns right.
+    /// - `speed` is the current speed of the car.
+    pub fn set_path_error(&mut self, cross_track: f64, heading_error: f64, speed: f64) -> f64 {
+        let Self {
+            cross_track_gain,
+            softening_speed,
+            max_steering_angle,
+            ..
+        } = *self;
+
+        let cross_track_term =
+            (cross_track_gain * cross_track).atan2(softening_speed + speed.abs());
+        let steering_angle =
+            (heading_error + cross_track_term).clamp(-max_steering_angle, max_steering_angle);
+        self.steering_angle = steering_angle;
+        steering_angle
+    }
+
+    pub fn steering_angle(&self) -> f64 {
+        self.steering_angle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::VehicleClass;
+
+    fn controller() -> LateralController {
+        LateralControllerInit::from_physics(&VehiclePhysics::preset(VehicleClass::Sedan)).build()
+    }
+
+    #[test]
+    fn cross_track_error_steers_back_to_path() {
+        let mut controller = controller();
+        // Left of the path steers to the right
+        assert!(controller.set_path_error(1.0, 0.0, 5.0) > 0.0);
+        assert!(controller.set_path_error(-1.0, 0.0, 5.0) < 0.0);
+        // The heading error passes through on the path
+        assert_eq!(controller.set_path_error(0.0, 0.2, 5.0), 0.2);
+        assert_eq!(controller.steering_angle(), 0.2);
+    }
+
+    #[test]
+    fn steering_is_limited() {
+        let mut controller = controller();
+        let max_steering_angle = 70f64.to_radians();
+        assert_eq!(
+            controller.set_path_error(100.0, 1.0, 0.0),
+            max_steering_angle
+        );
+        assert_eq!(
+            controller.set_path_error(-100.0, -1.0, 0.0),
+            -max_steering_angle
+        );
+    }
+}
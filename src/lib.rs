@@ -1,11 +1,23 @@
 pub mod accel_control;
+pub mod calibration;
 pub mod constants;
+pub mod effort;
+pub mod filter;
+pub mod gap_control;
+pub mod instability;
+pub mod lateral_control;
 pub mod physics;
 pub mod pid;
+pub mod sensors;
 pub mod speed_control;
 pub mod steer_control;
 pub mod vehicle_control;
 
+pub use constants::{
+    Thresholds, DEFAULT_MAX_STEERING_DEGREES, FULL_STOP_SPEED_MS, INTERNAL_ACCEL_MS2,
+    MIN_SLIP_REFERENCE_SPEED_MS, STAND_STILL_SPEED_MS,
+};
 pub use vehicle_control::{
-    Output, Report, Status, TargetRequest, VehicleController, VehicleControllerInit,
+    Output, OutputError, PedalCurve, PedalCurveError, ReplayStep, Report, Status, TargetRequest,
+    VehicleController, VehicleControllerInit, VerboseReport,
 };
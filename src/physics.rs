@@ -1,8 +1,27 @@
-use crate::constants::DEFAULT_MAX_STEERING_DEGREES;
+use crate::{constants::DEFAULT_MAX_STEERING_DEGREES, filter::LowPassFilter};
 use carla::rpc::VehiclePhysicsControl;
 use noisy_float::types::r64;
+use std::{error::Error, fmt, sync::Arc};
 
 const ACCELERATION_OF_GRAVITY: f64 = 9.81;
+const ROLLING_RESISTANCE_COEFFICIENT: f64 = 0.01;
+const DEFAULT_AERODYNAMIC_DRAG_COEFFICIENT: f64 = 0.3;
+const DEFAULT_DRAG_REFERENCE_AREA: f64 = 2.37;
+const RHO_AIR_25: f64 = 1.184;
+const DEFAULT_WHEEL_RADIUS_M: f64 = 0.35;
+const DEFAULT_MAX_SPEED_KMH: f64 = 180.0;
+const DEFAULT_MAX_ACCEL_MS2: f64 = 3.0;
+const DEFAULT_MAX_DECEL_MS2: f64 = 8.0;
+
+/// Common vehicle classes with preset physics, see [VehiclePhysics::preset].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VehicleClass {
+    Sedan,
+    SUV,
+    Truck,
+    SportsCar,
+    Motorcycle,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct VehiclePhysics {
@@ -11,32 +30,150 @@ pub struct VehiclePhysics {
     lay_off_engine_acceleration: f64,
     weight_force: f64,
     rolling_resistance_force: f64,
+    drag_area: f64,
+    wheel_radius: f64,
     max_steering_angle: f64,
     max_speed: f64,
     max_acceleration: f64,
     max_deceleration: f64,
 }
 
+/// The error returned by [VehiclePhysics::try_new] for inconsistent
+/// physics parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhysicsError {
+    NonPositiveMass(f64),
+    NoWheels,
+}
+
+impl fmt::Display for PhysicsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NonPositiveMass(mass) => write!(f, "mass {mass} is not positive"),
+            Self::NoWheels => write!(f, "the vehicle has no wheels"),
+        }
+    }
+}
+
+impl Error for PhysicsError {}
+
 impl VehiclePhysics {
+    /// Creates the physics like [try_new](VehiclePhysics::try_new),
+    /// except that a vehicle without wheels falls back to
+    /// [DEFAULT_MAX_STEERING_DEGREES] and a 0.35 m wheel radius.
+    ///
+    /// # Panics
+    /// It panics if the mass is non-positive or non-finite.
     pub fn new(physics_control: &VehiclePhysicsControl) -> Self {
+        match Self::try_new(physics_control) {
+            Ok(physics) => physics,
+            Err(PhysicsError::NoWheels) => Self::from_control(physics_control),
+            Err(err) => panic!("invalid vehicle physics: {err}"),
+        }
+    }
+
+    /// Creates the physics from CARLA's physics control, rejecting a
+    /// non-positive or non-finite mass and a vehicle without wheels.
+    ///
+    /// The torque curve is not read, so an empty curve is harmless,
+    /// though it is warned about once with the `log` feature. The max
+    /// speed, accel and decel are fixed at 180 km/h, 3 m/s² and 8 m/s²
+    /// for every vehicle.
+    pub fn try_new(physics_control: &VehiclePhysicsControl) -> Result<Self, PhysicsError> {
         let VehiclePhysicsControl {
             mass, ref wheels, ..
         } = *physics_control;
-        let mass = mass as f64;
-        let rolling_resistance_coefficient = 0.01;
-        let engine_brake_force = 500.0;
-        let lay_off_engine_acceleration = -engine_brake_force / mass;
-        let weight_force = mass * ACCELERATION_OF_GRAVITY;
-        let rolling_resistance_force = rolling_resistance_coefficient * weight_force;
+        if !(mass > 0.0 && mass.is_finite()) {
+            return Err(PhysicsError::NonPositiveMass(mass as f64));
+        }
+        if wheels.is_empty() {
+            return Err(PhysicsError::NoWheels);
+        }
+        Ok(Self::from_control(physics_control))
+    }
+
+    fn from_control(physics_control: &VehiclePhysicsControl) -> Self {
+        let VehiclePhysicsControl {
+            mass, ref wheels, ..
+        } = *physics_control;
+        #[cfg(feature = "log")]
+        if physics_control.torque_curve.is_empty() {
+            static EMPTY_TORQUE_CURVE: std::sync::Once = std::sync::Once::new();
+            EMPTY_TORQUE_CURVE.call_once(|| {
+                log::warn!("the torque curve is empty, falling back to the default accel limits");
+            });
+        }
+        let drag_area = DEFAULT_AERODYNAMIC_DRAG_COEFFICIENT * DEFAULT_DRAG_REFERENCE_AREA;
         let max_steering_angle = wheels
             .iter()
             .map(|wheel| r64(wheel.max_steer_angle as f64))
             .max()
             .map(|val| val.raw())
             .unwrap_or_else(|| DEFAULT_MAX_STEERING_DEGREES.to_radians());
-        let max_speed = 180.0 / 3.6;
-        let max_accel = 3.0;
-        let max_deceleration = 8.0;
+        // CARLA reports the wheel radius in centimeters.
+        let wheel_radius = wheels
+            .iter()
+            .map(|wheel| r64(wheel.radius as f64 / 100.0))
+            .max()
+            .map(|val| val.raw())
+            .unwrap_or(DEFAULT_WHEEL_RADIUS_M);
+
+        Self::from_parts(
+            mass as f64,
+            drag_area,
+            wheel_radius,
+            max_steering_angle,
+            DEFAULT_MAX_SPEED_KMH / 3.6,
+            DEFAULT_MAX_ACCEL_MS2,
+            DEFAULT_MAX_DECEL_MS2,
+        )
+    }
+
+    /// Creates the physics of a typical vehicle of the given class
+    /// without a [VehiclePhysicsControl], e.g. for offline prototyping.
+    pub fn preset(class: VehicleClass) -> Self {
+        // (mass kg, drag area m², wheel radius m, max steer deg,
+        //  max speed km/h, max accel m/s², max decel m/s²)
+        let (
+            mass,
+            drag_area,
+            wheel_radius,
+            max_steering_degrees,
+            max_speed_kmh,
+            max_accel,
+            max_decel,
+        ) = match class {
+            VehicleClass::Sedan => (1500.0, 0.65, 0.33, 70.0, 180.0, 3.0, 8.0),
+            VehicleClass::SUV => (2100.0, 0.95, 0.37, 65.0, 170.0, 2.5, 7.5),
+            VehicleClass::Truck => (9000.0, 4.5, 0.50, 45.0, 110.0, 1.0, 5.0),
+            VehicleClass::SportsCar => (1350.0, 0.60, 0.34, 70.0, 280.0, 6.0, 10.0),
+            VehicleClass::Motorcycle => (250.0, 0.50, 0.30, 35.0, 200.0, 5.0, 9.0),
+        };
+
+        Self::from_parts(
+            mass,
+            drag_area,
+            wheel_radius,
+            f64::to_radians(max_steering_degrees),
+            max_speed_kmh / 3.6,
+            max_accel,
+            max_decel,
+        )
+    }
+
+    fn from_parts(
+        mass: f64,
+        drag_area: f64,
+        wheel_radius: f64,
+        max_steering_angle: f64,
+        max_speed: f64,
+        max_accel: f64,
+        max_deceleration: f64,
+    ) -> Self {
+        let engine_brake_force = 500.0;
+        let lay_off_engine_acceleration = -engine_brake_force / mass;
+        let weight_force = mass * ACCELERATION_OF_GRAVITY;
+        let rolling_resistance_force = ROLLING_RESISTANCE_COEFFICIENT * weight_force;
 
         Self {
             mass,
@@ -44,6 +181,8 @@ impl VehiclePhysics {
             lay_off_engine_acceleration,
             weight_force,
             rolling_resistance_force,
+            drag_area,
+            wheel_radius,
             max_steering_angle,
             max_speed,
             max_acceleration: max_accel,
@@ -60,6 +199,7 @@ impl VehiclePhysics {
         let Self {
             mass,
             rolling_resistance_force,
+            drag_area,
             ..
         } = *self;
         let speed_squared = speed.powi(2);
@@ -69,17 +209,101 @@ impl VehiclePhysics {
         } else {
             slope_force_value
         };
-        let aerodynamic_drag_force = {
-            let default_aerodynamic_drag_coefficient = 0.3;
-            let default_drag_reference_area = 2.37;
-            let drag_area = default_aerodynamic_drag_coefficient * default_drag_reference_area;
-            let rho_air_25 = 1.184;
-            0.5 * drag_area * rho_air_25 * speed_squared
-        };
+        let aerodynamic_drag_force = 0.5 * drag_area * RHO_AIR_25 * speed_squared;
 
         -(rolling_resistance_force + aerodynamic_drag_force + slope_force) / mass
     }
 
+    /// Computes the resistive force in Newtons, including rolling
+    /// resistance, aerodynamic drag and slope force.
+    ///
+    /// The force is negative when it decelerates the vehicle.
+    pub fn driving_impedance_force(&self, speed: f64, pitch_radians: f64, reverse: bool) -> f64 {
+        self.driving_impedance_acceleration(speed, pitch_radians, reverse) * self.mass
+    }
+
+    /// Computes the borders on the target pedal, returned as
+    /// `(throttle_lower_border, brake_upper_border)`.
+    ///
+    /// Above the throttle lower border the vehicle accelerates, below
+    /// the brake upper border it brakes, and in between it coasts.
+    pub fn pedal_borders(&self, speed: f64, pitch_radians: f64, reverse: bool) -> (f64, f64) {
+        let throttle_lower_border =
+            self.driving_impedance_acceleration(speed, pitch_radians, reverse);
+        let brake_upper_border = throttle_lower_border + self.lay_off_engine_acceleration;
+        (throttle_lower_border, brake_upper_border)
+    }
+
+    /// Computes the highest sustainable speed in m/s on a road with
+    /// `pitch_radians`, where the max acceleration at full throttle
+    /// balances the resistive acceleration. The result is capped by
+    /// the max speed and is zero if the vehicle cannot move at all.
+    ///
+    /// The pitch follows the sign convention of
+    /// [driving_impedance_acceleration](VehiclePhysics::driving_impedance_acceleration).
+    pub fn max_speed_on_grade(&self, pitch_radians: f64) -> f64 {
+        let Self {
+            mass,
+            drag_area,
+            max_acceleration,
+            max_speed,
+            ..
+        } = *self;
+
+        // net(v) = max_accel + impedance(0) - drag_coefficient * v²
+        let standstill_net_accel =
+            max_acceleration + self.driving_impedance_acceleration(0.0, pitch_radians, false);
+        let drag_coefficient = 0.5 * drag_area * RHO_AIR_25 / mass;
+
+        if standstill_net_accel <= 0.0 {
+            0.0
+        } else if drag_coefficient <= 0.0 {
+            max_speed
+        } else {
+            (standstill_net_accel / drag_coefficient)
+                .sqrt()
+                .min(max_speed)
+        }
+    }
+
+    /// Updates the vehicle mass and recomputes the mass-dependent
+    /// forces, e.g. when a trailer is hitched.
+    pub fn set_mass(&mut self, mass: f64) {
+        let weight_force = mass * ACCELERATION_OF_GRAVITY;
+        self.mass = mass;
+        self.weight_force = weight_force;
+        self.rolling_resistance_force = ROLLING_RESISTANCE_COEFFICIENT * weight_force;
+        self.lay_off_engine_acceleration = -self.engine_brake_force / mass;
+    }
+
+    /// Blends the physics linearly toward `other` by `t` in [0, 1],
+    /// e.g. for a slowly varying load.
+    ///
+    /// The base parameters are interpolated and the mass-dependent
+    /// forces are recomputed from the blended mass.
+    pub fn lerp(&self, other: &VehiclePhysics, t: f64) -> VehiclePhysics {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |lhs: f64, rhs: f64| lhs + (rhs - lhs) * t;
+
+        let mut physics = Self {
+            engine_brake_force: mix(self.engine_brake_force, other.engine_brake_force),
+            drag_area: mix(self.drag_area, other.drag_area),
+            wheel_radius: mix(self.wheel_radius, other.wheel_radius),
+            max_steering_angle: mix(self.max_steering_angle, other.max_steering_angle),
+            max_speed: mix(self.max_speed, other.max_speed),
+            max_acceleration: mix(self.max_acceleration, other.max_acceleration),
+            max_deceleration: mix(self.max_deceleration, other.max_deceleration),
+            ..self.clone()
+        };
+        physics.set_mass(mix(self.mass, other.mass));
+        physics
+    }
+
+    /// Updates the aerodynamic drag area (drag coefficient × reference area) in m².
+    pub fn set_drag_area(&mut self, drag_area: f64) {
+        self.drag_area = drag_area;
+    }
+
     pub fn engine_brake_force(&self) -> f64 {
         self.engine_brake_force
     }
@@ -100,6 +324,15 @@ impl VehiclePhysics {
         self.rolling_resistance_force
     }
 
+    pub fn drag_area(&self) -> f64 {
+        self.drag_area
+    }
+
+    /// Gets the wheel radius in meters.
+    pub fn wheel_radius(&self) -> f64 {
+        self.wheel_radius
+    }
+
     pub fn max_steering_angle(&self) -> f64 {
         self.max_steering_angle
     }
@@ -115,4 +348,401 @@ impl VehiclePhysics {
     pub fn max_deceleration(&self) -> f64 {
         self.max_deceleration
     }
+
+    /// Collects the derived physics values into a [PhysicsSummary].
+    pub fn summary(&self) -> PhysicsSummary {
+        PhysicsSummary {
+            mass: self.mass,
+            max_steering_angle_degrees: self.max_steering_angle.to_degrees(),
+            max_speed: self.max_speed,
+            max_accel: self.max_acceleration,
+            max_deceleration: self.max_deceleration,
+            drag_area: self.drag_area,
+            rolling_resistance_force: self.rolling_resistance_force,
+            lay_off_engine_acceleration: self.lay_off_engine_acceleration,
+        }
+    }
+}
+
+/// A user-supplied resistive acceleration model.
+///
+/// The function receives `(speed, pitch_radians, reverse)` and returns
+/// the resistive acceleration in m/s² like
+/// [VehiclePhysics::driving_impedance_acceleration].
+#[derive(Clone)]
+pub struct ResistiveModel(Arc<dyn Fn(f64, f64, bool) -> f64 + Send + Sync>);
+
+impl ResistiveModel {
+    pub fn new<F>(model: F) -> Self
+    where
+        F: Fn(f64, f64, bool) -> f64 + Send + Sync + 'static,
+    {
+        Self(Arc::new(model))
+    }
+
+    pub fn acceleration(&self, speed: f64, pitch_radians: f64, reverse: bool) -> f64 {
+        (self.0)(speed, pitch_radians, reverse)
+    }
+}
+
+impl fmt::Debug for ResistiveModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResistiveModel").finish_non_exhaustive()
+    }
+}
+
+/// A disturbance observer estimating the road grade from the
+/// discrepancy between the modeled and the measured acceleration.
+///
+/// The estimate is the grade not accounted for by the pitch input of
+/// the model, so it is the full grade when the pitch input is zero.
+#[derive(Debug, Clone)]
+pub struct GradeEstimator {
+    filter: LowPassFilter,
+    expected_accel: Option<f64>,
+}
+
+impl GradeEstimator {
+    /// Creates an estimator smoothing the disturbance with a low-pass
+    /// filter of `time_constant` seconds.
+    pub fn new(time_constant: f64) -> Self {
+        Self {
+            filter: LowPassFilter::new(time_constant),
+            expected_accel: None,
+        }
+    }
+
+    /// Records the acceleration the model expects from the command
+    /// applied until the next [update](GradeEstimator::update).
+    pub fn set_expected_accel(&mut self, expected_accel: Option<f64>) {
+        self.expected_accel = expected_accel;
+    }
+
+    /// Compares the measured acceleration resulting from the last
+    /// command against the expected one and returns the estimate.
+    pub fn update(&mut self, measured_accel: f64, time_delta_sec: f64) -> Option<f64> {
+        if let Some(expected_accel) = self.expected_accel.take() {
+            let disturbance = measured_accel - expected_accel;
+            self.filter.update(disturbance, time_delta_sec);
+        }
+        self.grade_radians()
+    }
+
+    /// Gets the estimated grade in radians with the sign convention of
+    /// the pitch input, or `None` before the first comparison.
+    pub fn grade_radians(&self) -> Option<f64> {
+        let disturbance = self.filter.value()?;
+        Some(
+            (disturbance / ACCELERATION_OF_GRAVITY)
+                .clamp(-1.0, 1.0)
+                .asin(),
+        )
+    }
+
+    pub fn reset(&mut self) {
+        self.filter.reset();
+        self.expected_accel = None;
+    }
+}
+
+/// Computes the longitudinal accel (m/s²) left on the friction circle
+/// of radius `friction_coefficient * g` after the lateral accel.
+///
+/// Returns zero once the lateral accel uses up the available grip.
+pub fn longitudinal_grip_budget(lateral_accel: f64, friction_coefficient: f64) -> f64 {
+    let max_accel = friction_coefficient.abs() * ACCELERATION_OF_GRAVITY;
+    (max_accel.powi(2) - lateral_accel.powi(2)).max(0.0).sqrt()
+}
+
+/// Converts the longitudinal specific force (m/s²) read by an
+/// accelerometer on a slope to the accel of the vehicle by adding back
+/// the gravity component along the slope.
+///
+/// The pitch follows the sign convention of
+/// [driving_impedance_acceleration](VehiclePhysics::driving_impedance_acceleration),
+/// where a positive pitch assists the forward motion.
+pub fn gravity_compensated_accel(specific_force: f64, pitch_radians: f64) -> f64 {
+    specific_force + ACCELERATION_OF_GRAVITY * pitch_radians.sin()
+}
+
+/// Estimates the aerodynamic drag area online from the deceleration
+/// observed while coasting.
+///
+/// It fits the drag area (m²) to the v² drag model by least squares
+/// over all coasting samples, taking the rolling resistance and the
+/// slope from [VehiclePhysics].
+#[derive(Debug, Clone)]
+pub struct DragAreaEstimator {
+    min_speed: f64,
+    coasting: bool,
+    sum_xy: f64,
+    sum_xx: f64,
+}
+
+impl DragAreaEstimator {
+    /// Creates an estimator that ignores samples below `min_speed`
+    /// (m/s), where the drag is too small to observe.
+    pub fn new(min_speed: f64) -> Self {
+        Self {
+            min_speed,
+            coasting: false,
+            sum_xy: 0.0,
+            sum_xx: 0.0,
+        }
+    }
+
+    /// Records whether the command applied until the next
+    /// [update](DragAreaEstimator::update) lets the vehicle coast.
+    pub fn set_coasting(&mut self, coasting: bool) {
+        self.coasting = coasting;
+    }
+
+    /// Fits the measured acceleration resulting from the last command
+    /// if it was coasting and returns the estimate.
+    pub fn update(
+        &mut self,
+        physics: &VehiclePhysics,
+        speed: f64,
+        measured_accel: f64,
+        pitch_radians: f64,
+        reverse: bool,
+    ) -> Option<f64> {
+        if std::mem::take(&mut self.coasting) && speed.abs() >= self.min_speed {
+            // accel = drag_free_accel - drag_area * x
+            let x = 0.5 * RHO_AIR_25 * speed.powi(2) / physics.mass();
+            let drag_free_accel =
+                physics.driving_impedance_acceleration(speed, pitch_radians, reverse)
+                    + physics.drag_area() * x;
+            let y = drag_free_accel - measured_accel;
+            self.sum_xy += x * y;
+            self.sum_xx += x * x;
+        }
+        self.drag_area()
+    }
+
+    /// Gets the estimated drag area in m², or `None` before the first
+    /// coasting sample.
+    pub fn drag_area(&self) -> Option<f64> {
+        (self.sum_xx > 0.0).then(|| (self.sum_xy / self.sum_xx).max(0.0))
+    }
+
+    pub fn reset(&mut self) {
+        self.coasting = false;
+        self.sum_xy = 0.0;
+        self.sum_xx = 0.0;
+    }
+}
+
+/// A read-only summary of the values derived by [VehiclePhysics].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicsSummary {
+    /// Vehicle mass in kg.
+    pub mass: f64,
+    /// Maximum steering angle in degrees.
+    pub max_steering_angle_degrees: f64,
+    /// Maximum speed in m/s.
+    pub max_speed: f64,
+    /// Maximum acceleration in m/s².
+    pub max_accel: f64,
+    /// Maximum deceleration in m/s².
+    pub max_deceleration: f64,
+    /// Aerodynamic drag area in m².
+    pub drag_area: f64,
+    /// Rolling resistance force in N.
+    pub rolling_resistance_force: f64,
+    /// Acceleration when the engine is laid off in m/s².
+    pub lay_off_engine_acceleration: f64,
+}
+
+impl fmt::Display for PhysicsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            mass,
+            max_steering_angle_degrees,
+            max_speed,
+            max_accel,
+            max_deceleration,
+            drag_area,
+            rolling_resistance_force,
+            lay_off_engine_acceleration,
+        } = *self;
+        write!(
+            f,
+            "mass={mass:.1}kg max_steer={max_steering_angle_degrees:.1}deg \
+             max_speed={max_speed:.2}m/s max_accel={max_accel:.2}m/s² \
+             max_decel={max_deceleration:.2}m/s² drag_area={drag_area:.3}m² \
+             rolling_resistance={rolling_resistance_force:.1}N \
+             lay_off_accel={lay_off_engine_acceleration:.3}m/s²"
+        )
+    }
+}
+
+impl fmt::Display for VehiclePhysics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.summary().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use carla::rpc::{GearPhysicsControl, WheelPhysicsControl};
+    use std::mem::ManuallyDrop;
+
+    // The gear and wheel destructors live in the CARLA library, so the
+    // control is never dropped and its lists are built without calls
+    // that could unwind and drop them.
+    const NO_GEARS: Vec<GearPhysicsControl> = Vec::new();
+    const NO_WHEELS: Vec<WheelPhysicsControl> = Vec::new();
+
+    fn physics_control(mass: f32) -> ManuallyDrop<VehiclePhysicsControl> {
+        ManuallyDrop::new(VehiclePhysicsControl {
+            torque_curve: vec![],
+            max_rpm: 5000.0,
+            moi: 1.0,
+            damping_rate_full_throttle: 0.15,
+            damping_rate_zero_throttle_clutch_engaged: 2.0,
+            damping_rate_zero_throttle_clutch_disengaged: 0.35,
+            use_gear_autobox: true,
+            gear_switch_time: 0.5,
+            clutch_strength: 10.0,
+            final_ratio: 4.0,
+            mass,
+            drag_coefficient: 0.3,
+            center_of_mass: [0.0; 3].into(),
+            steering_curve: vec![],
+            use_sweep_wheel_collision: false,
+            forward_gears: NO_GEARS,
+            wheels: NO_WHEELS,
+        })
+    }
+
+    #[test]
+    fn invalid_mass_is_rejected() {
+        assert_eq!(
+            VehiclePhysics::try_new(&physics_control(0.0)),
+            Err(PhysicsError::NonPositiveMass(0.0))
+        );
+        assert!(VehiclePhysics::try_new(&physics_control(f32::NAN)).is_err());
+    }
+
+    #[test]
+    fn vehicle_without_wheels_falls_back_to_defaults() {
+        let control = physics_control(1500.0);
+        assert_eq!(
+            VehiclePhysics::try_new(&control),
+            Err(PhysicsError::NoWheels)
+        );
+
+        let physics = VehiclePhysics::new(&control);
+        assert_eq!(
+            physics.max_steering_angle(),
+            DEFAULT_MAX_STEERING_DEGREES.to_radians()
+        );
+        assert_eq!(physics.wheel_radius(), DEFAULT_WHEEL_RADIUS_M);
+        assert_eq!(physics.mass(), 1500.0);
+    }
+
+    #[test]
+    fn empty_torque_curve_falls_back_to_default_limits() {
+        let control = physics_control(1500.0);
+        assert!(control.torque_curve.is_empty());
+
+        let physics = VehiclePhysics::new(&control);
+        assert_eq!(physics.max_speed(), 180.0 / 3.6);
+        assert_eq!(physics.max_accel(), 3.0);
+    }
+
+    #[test]
+    fn grip_budget_shrinks_with_lateral_accel() {
+        assert!((longitudinal_grip_budget(0.0, 1.0) - ACCELERATION_OF_GRAVITY).abs() < 1e-12);
+        let budget = longitudinal_grip_budget(6.0, 1.0);
+        assert!((budget.powi(2) + 36.0 - ACCELERATION_OF_GRAVITY.powi(2)).abs() < 1e-9);
+        assert_eq!(longitudinal_grip_budget(-12.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn gravity_compensation_cancels_slope_reading() {
+        // Standing on a slope that assists the forward motion
+        let pitch_radians: f64 = 0.1;
+        let specific_force = -ACCELERATION_OF_GRAVITY * pitch_radians.sin();
+        assert!(gravity_compensated_accel(specific_force, pitch_radians).abs() < 1e-12);
+    }
+
+    #[test]
+    fn max_speed_on_grade_balances_full_throttle() {
+        let physics = VehiclePhysics::preset(VehicleClass::Truck);
+        let flat_speed = physics.max_speed_on_grade(0.0);
+        assert!(flat_speed <= physics.max_speed());
+        assert!(physics.max_speed_on_grade(-0.08) < flat_speed);
+        assert_eq!(physics.max_speed_on_grade(-0.5), 0.0);
+    }
+
+    #[test]
+    fn lerp_blends_toward_other_physics() {
+        let sedan = VehiclePhysics::preset(VehicleClass::Sedan);
+        let truck = VehiclePhysics::preset(VehicleClass::Truck);
+        assert_eq!(sedan.lerp(&truck, 0.0), sedan);
+        assert_eq!(sedan.lerp(&truck, 2.0).mass(), truck.mass());
+        let half = sedan.lerp(&truck, 0.5);
+        assert_eq!(half.mass(), 5250.0);
+        assert!((half.weight_force() - 5250.0 * ACCELERATION_OF_GRAVITY).abs() < 1e-9);
+    }
+
+    #[test]
+    fn set_mass_recomputes_forces() {
+        let sedan = VehiclePhysics::preset(VehicleClass::Sedan);
+        let mut heavy = sedan.clone();
+        heavy.set_mass(3000.0);
+        assert!((heavy.weight_force() - 3000.0 * ACCELERATION_OF_GRAVITY).abs() < 1e-9);
+        assert!(
+            (heavy.lay_off_engine_acceleration() - sedan.lay_off_engine_acceleration() / 2.0).abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn grade_estimator_recovers_unmodeled_grade() {
+        let mut estimator = GradeEstimator::new(0.5);
+        assert_eq!(estimator.update(1.0, 0.05), None);
+
+        let grade_radians: f64 = 0.05;
+        for _ in 0..100 {
+            estimator.set_expected_accel(Some(1.0));
+            estimator.update(1.0 + ACCELERATION_OF_GRAVITY * grade_radians.sin(), 0.05);
+        }
+        assert!((estimator.grade_radians().unwrap() - grade_radians).abs() < 1e-9);
+    }
+
+    #[test]
+    fn drag_area_estimator_fits_coasting_samples() {
+        let physics = VehiclePhysics::preset(VehicleClass::Sedan);
+        let mut actual = physics.clone();
+        actual.set_drag_area(0.9);
+
+        let mut estimator = DragAreaEstimator::new(5.0);
+        estimator.set_coasting(true);
+        assert_eq!(estimator.update(&physics, 2.0, -0.1, 0.0, false), None);
+        // Samples without coasting are ignored
+        assert_eq!(estimator.update(&physics, 20.0, 0.0, 0.0, false), None);
+
+        for speed in [10.0, 20.0, 30.0] {
+            estimator.set_coasting(true);
+            let measured_accel = actual.driving_impedance_acceleration(speed, 0.0, false);
+            estimator.update(&physics, speed, measured_accel, 0.0, false);
+        }
+        assert!((estimator.drag_area().unwrap() - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn errors_describe_the_parameter() {
+        assert_eq!(
+            PhysicsError::NonPositiveMass(-1.0).to_string(),
+            "mass -1 is not positive"
+        );
+        assert_eq!(
+            PhysicsError::NoWheels.to_string(),
+            "the vehicle has no wheels"
+        );
+    }
 }
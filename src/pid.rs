@@ -1,4 +1,6 @@
+pub use pid::ControlOutput;
 use pid::Pid;
+use std::fmt;
 
 #[derive(Debug, Clone)]
 pub struct PidInit {
@@ -6,6 +8,9 @@ pub struct PidInit {
     pub ki: f64,
     pub kd: f64,
     pub output_limit: f64,
+    /// The bound on the magnitude of the integral term, which keeps
+    /// it from winding up under a persistent error.
+    pub integral_limit: f64,
 }
 
 impl PidInit {
@@ -15,7 +20,123 @@ impl PidInit {
             ki,
             kd,
             output_limit,
+            integral_limit,
         } = *self;
-        Pid::new(kp, ki, kd, f64::MAX, f64::MAX, f64::MAX, output_limit, 0.0)
+        Pid::new(
+            kp,
+            ki,
+            kd,
+            f64::MAX,
+            integral_limit,
+            f64::MAX,
+            output_limit,
+            0.0,
+        )
+    }
+}
+
+/// The terms of a PID output, see [ControlOutput].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PidTerms {
+    pub p: f64,
+    pub i: f64,
+    pub d: f64,
+    pub output: f64,
+}
+
+impl From<&ControlOutput<f64>> for PidTerms {
+    fn from(output: &ControlOutput<f64>) -> Self {
+        Self {
+            p: output.p,
+            i: output.i,
+            d: output.d,
+            output: output.output,
+        }
+    }
+}
+
+/// Identifies the PID reported to a [PidHook].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PidChannel {
+    Speed,
+    Accel,
+}
+
+/// A callback receiving the raw PID outputs, see
+/// [VehicleController::on_pid_output](crate::VehicleController::on_pid_output).
+pub struct PidHook(Box<dyn FnMut(PidChannel, ControlOutput<f64>) + Send>);
+
+impl PidHook {
+    pub fn new<F>(hook: F) -> Self
+    where
+        F: FnMut(PidChannel, ControlOutput<f64>) + Send + 'static,
+    {
+        Self(Box::new(hook))
+    }
+
+    pub fn call(&mut self, channel: PidChannel, output: ControlOutput<f64>) {
+        (self.0)(channel, output)
+    }
+}
+
+impl fmt::Debug for PidHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PidHook").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_is_limited() {
+        let init = PidInit {
+            kp: 10.0,
+            ki: 0.0,
+            kd: 0.0,
+            output_limit: 1.0,
+            integral_limit: 1.0,
+        };
+        let mut pid = init.build();
+        pid.setpoint = 1.0;
+        let output = pid.next_control_output(0.0);
+        assert_eq!(output.p, 10.0);
+        assert_eq!(PidTerms::from(&output).output, 1.0);
+    }
+
+    #[test]
+    fn hook_receives_channel() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut hook = PidHook::new(move |channel, output: ControlOutput<f64>| {
+            sender.send((channel, output.output)).unwrap();
+        });
+        let mut pid = PidInit {
+            kp: 0.5,
+            ki: 0.0,
+            kd: 0.0,
+            output_limit: 1.0,
+            integral_limit: 1.0,
+        }
+        .build();
+        pid.setpoint = 1.0;
+        hook.call(PidChannel::Accel, pid.next_control_output(0.0));
+        assert_eq!(receiver.recv().unwrap(), (PidChannel::Accel, 0.5));
+    }
+
+    #[test]
+    fn integral_is_limited() {
+        let mut pid = PidInit {
+            kp: 0.0,
+            ki: 0.1,
+            kd: 0.0,
+            output_limit: 1.0,
+            integral_limit: 0.3,
+        }
+        .build();
+        pid.setpoint = 1.0;
+        let integrals: Vec<f64> = (0..10).map(|_| pid.next_control_output(0.0).i).collect();
+        assert!((integrals[1] - 0.2).abs() < 1e-12);
+        assert_eq!(*integrals.last().unwrap(), 0.3);
     }
 }
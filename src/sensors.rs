@@ -0,0 +1,43 @@
+use carla::client::{ActorBase, Vehicle};
+
+/// The measurements a [VehicleController](crate::VehicleController)
+/// reads from a vehicle, see
+/// [step_from_sensors](crate::VehicleController::step_from_sensors).
+pub trait VehicleSensors {
+    /// The speed magnitude in m/s.
+    fn speed(&self) -> f64;
+
+    /// The pitch in radians.
+    fn pitch(&self) -> f64;
+
+    /// The longitudinal accel in m/s² along the vehicle x-axis if
+    /// measured directly, e.g. by an IMU. The controller differences
+    /// the speed if it is `None`.
+    fn accel(&self) -> Option<f64> {
+        None
+    }
+}
+
+/// Available with the CARLA library, i.e. without the `docs-only`
+/// feature.
+#[cfg(not(feature = "docs-only"))]
+impl VehicleSensors for Vehicle {
+    fn speed(&self) -> f64 {
+        vehicle_speed(self)
+    }
+
+    fn pitch(&self) -> f64 {
+        vehicle_pitch(self)
+    }
+}
+
+/// Reads the speed magnitude of `vehicle` in m/s.
+pub(crate) fn vehicle_speed(vehicle: &Vehicle) -> f64 {
+    vehicle.velocity().norm() as f64
+}
+
+/// Reads the pitch of `vehicle` in radians.
+pub(crate) fn vehicle_pitch(vehicle: &Vehicle) -> f64 {
+    let (_, pitch, _) = vehicle.transform().rotation.euler_angles();
+    pitch as f64
+}
@@ -1,17 +1,57 @@
-use crate::{
-    constants::{FULL_STOP_SPEED_MS, INTERNAL_ACCEL_MS2, STAND_STILL_SPEED_MS},
-    physics::VehiclePhysics,
-    pid::PidInit,
-};
-use pid::Pid;
+use crate::{constants::Thresholds, physics::VehiclePhysics, pid::PidInit};
+use pid::{ControlOutput, Pid};
+
+/// The meaning of the target accel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccelMode {
+    /// The target accel bounds the accel the speed PID may command.
+    Limit,
+    /// The target accel is tracked directly until the vehicle reaches
+    /// the target speed, which acts as a ceiling when accelerating and
+    /// as a floor when decelerating.
+    Setpoint,
+}
 
 #[derive(Debug, Clone)]
 pub struct SpeedControllerInit {
     pub pid: PidInit,
     pub max_speed: f64,
     pub max_accel: f64,
+    /// The minimum target accel that engages the speed PID when
+    /// `min_accel_gating` is set. It must not exceed `max_accel` and
+    /// is clamped to `max_accel` otherwise.
     pub min_accel: f64,
     pub max_decel: f64,
+    /// If set, the speed PID engages only when the absolute target
+    /// accel reaches `min_accel`. Below that, the target accel is
+    /// applied open loop.
+    pub min_accel_gating: bool,
+    /// The width of the band around `min_accel`, in which the output
+    /// blends linearly between the open loop and the speed PID.
+    pub min_accel_band: f64,
+    /// The gain converting the speed in excess of `max_speed` (m/s)
+    /// to a deceleration (m/s²).
+    pub overspeed_gain: f64,
+    /// The speed and accel thresholds. The enclosing
+    /// [VehicleControllerInit](crate::VehicleControllerInit) replaces
+    /// them with its own.
+    pub thresholds: Thresholds,
+    pub accel_mode: AccelMode,
+    /// The factor on the speed PID output limit while the setpoint
+    /// accel is not negative.
+    pub accelerating_limit_scale: f64,
+    /// The factor on the speed PID output limit while the setpoint
+    /// accel is negative, e.g. below 1 for smoother stops.
+    pub braking_limit_scale: f64,
+    /// The deceleration (m/s²) that replaces the target accel when the
+    /// target speed is below the full stop speed. Defaults to
+    /// `max_decel` if unset and is clamped to it.
+    pub stopping_decel: Option<f64>,
+    /// If set, the setpoint accel (m/s²) is capped to this value while
+    /// pulling away below `launch_speed`, for a gentle launch.
+    pub launch_accel: Option<f64>,
+    /// The speed (m/s) below which `launch_accel` applies.
+    pub launch_speed: f64,
 }
 
 impl SpeedControllerInit {
@@ -22,11 +62,22 @@ impl SpeedControllerInit {
                 ki: 0.0,
                 kd: 0.5,
                 output_limit: 1.0,
+                integral_limit: 1.0,
             },
             max_speed: physics.max_speed(),
             max_accel: physics.max_accel(),
-            min_accel: min_accel.unwrap_or(1.0),
+            min_accel: min_accel.unwrap_or(1.0).min(physics.max_accel()),
             max_decel: physics.max_deceleration(),
+            min_accel_gating: false,
+            min_accel_band: 0.2,
+            overspeed_gain: 1.0,
+            thresholds: Thresholds::default(),
+            accel_mode: AccelMode::Limit,
+            accelerating_limit_scale: 1.0,
+            braking_limit_scale: 1.0,
+            stopping_decel: None,
+            launch_accel: None,
+            launch_speed: 2.0,
         }
     }
 
@@ -37,31 +88,67 @@ impl SpeedControllerInit {
             max_accel,
             min_accel,
             max_decel,
+            min_accel_gating,
+            min_accel_band,
+            overspeed_gain,
+            thresholds,
+            accel_mode,
+            accelerating_limit_scale,
+            braking_limit_scale,
+            stopping_decel,
+            launch_accel,
+            launch_speed,
         } = *self;
 
         SpeedController {
             speed_pid: pid.build(),
-            accel_activator: DelayedActivator::new(5),
+            speed_integral: 0.0,
             target_speed: 0.0,
             target_accel: 0.0,
             max_speed,
             max_accel,
-            min_accel,
+            min_accel: min_accel.min(max_accel),
             max_decel,
+            min_accel_gating,
+            min_accel_band,
+            overspeed_gain,
+            thresholds,
+            accel_mode,
+            accelerating_limit_scale,
+            braking_limit_scale,
+            stopping_decel: stopping_decel.map_or(max_decel, |decel| decel.abs().min(max_decel)),
+            accel_overridden: false,
+            decel_tracking: false,
+            speed_priority: 1.0,
+            launch_accel,
+            launch_speed,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpeedController {
     speed_pid: Pid<f64>,
-    accel_activator: DelayedActivator,
+    speed_integral: f64,
     target_speed: f64,
     target_accel: f64,
     max_speed: f64,
     max_accel: f64,
     min_accel: f64,
     max_decel: f64,
+    min_accel_gating: bool,
+    min_accel_band: f64,
+    overspeed_gain: f64,
+    thresholds: Thresholds,
+    accel_mode: AccelMode,
+    accelerating_limit_scale: f64,
+    braking_limit_scale: f64,
+    stopping_decel: f64,
+    accel_overridden: bool,
+    decel_tracking: bool,
+    speed_priority: f64,
+    launch_accel: Option<f64>,
+    launch_speed: f64,
 }
 
 impl SpeedController {
@@ -69,38 +156,151 @@ impl SpeedController {
         self.target_speed
     }
 
+    pub fn max_speed(&self) -> f64 {
+        self.max_speed
+    }
+
+    pub fn max_accel(&self) -> f64 {
+        self.max_accel
+    }
+
+    pub fn max_decel(&self) -> f64 {
+        self.max_decel
+    }
+
+    pub fn thresholds(&self) -> &Thresholds {
+        &self.thresholds
+    }
+
+    pub fn accel_mode(&self) -> AccelMode {
+        self.accel_mode
+    }
+
+    pub fn set_accel_mode(&mut self, accel_mode: AccelMode) {
+        self.accel_mode = accel_mode;
+    }
+
+    /// Blends the speed loop output with the target accel, weighting
+    /// the speed loop by `speed_priority` in [0, 1] and the target
+    /// accel by the rest. It defaults to 1, which tracks the speed
+    /// only. Full stops are not blended.
+    pub fn set_speed_priority(&mut self, speed_priority: f64) {
+        self.speed_priority = speed_priority.clamp(0.0, 1.0);
+    }
+
+    pub fn speed_priority(&self) -> f64 {
+        self.speed_priority
+    }
+
+    pub fn launch_accel(&self) -> Option<f64> {
+        self.launch_accel
+    }
+
+    pub fn launch_speed(&self) -> f64 {
+        self.launch_speed
+    }
+
+    /// Multiplies the proportional, integral and derivative gains of
+    /// the speed PID by `factor`.
+    pub fn scale_gains(&mut self, factor: f64) {
+        let pid = &mut self.speed_pid;
+        pid.kp *= factor;
+        pid.ki *= factor;
+        pid.kd *= factor;
+    }
+
+    /// Gets the integral term of the speed PID as of its last run.
+    pub fn integral(&self) -> f64 {
+        self.speed_integral
+    }
+
+    /// Gets the limit on the per-step accel delta of the speed PID.
+    pub fn output_limit(&self) -> f64 {
+        self.speed_pid.output_limit
+    }
+
+    /// Sets the target speed and accel, clamped to the limits.
+    ///
+    /// If the target speed is below the full stop speed, the target
+    /// accel is ignored and replaced by `-stopping_decel`, which
+    /// defaults to `-max_decel`.
+    /// [accel_overridden](SpeedController::accel_overridden) reports
+    /// whether it happened.
     pub fn set_target(&mut self, target_speed: f64, target_accel: f64) {
         let Self {
             max_speed,
             max_accel,
             max_decel,
+            thresholds,
+            stopping_decel,
             ..
         } = *self;
         let target_speed = target_speed.clamp(-max_speed, max_speed);
-        let target_accel = if target_speed.abs() >= FULL_STOP_SPEED_MS {
-            target_accel.clamp(-max_decel, max_accel)
+        let is_stopping = target_speed.abs() < thresholds.full_stop_speed;
+        let target_accel = if is_stopping {
+            -stopping_decel
         } else {
-            -max_decel
+            target_accel.clamp(-max_decel, max_accel)
         };
 
         self.target_speed = target_speed;
         self.target_accel = target_accel;
+        self.accel_overridden = is_stopping;
+        self.decel_tracking = false;
+    }
+
+    /// Returns true if the last [set_target](SpeedController::set_target)
+    /// replaced the target accel by the stopping deceleration.
+    pub fn accel_overridden(&self) -> bool {
+        self.accel_overridden
+    }
+
+    /// Targets a full stop with a deceleration bounded by `decel`
+    /// instead of `max_decel`.
+    pub fn set_stop_target(&mut self, decel: f64) {
+        self.target_speed = 0.0;
+        self.target_accel = -decel.abs().min(self.max_decel);
+        self.accel_overridden = false;
+        self.decel_tracking = false;
+    }
+
+    /// Targets a full stop and tracks the deceleration `decel`, bounded
+    /// by `max_decel`, in any [AccelMode] until the vehicle stands
+    /// still. The speed PID does not run meanwhile.
+    pub fn set_decel_target(&mut self, decel: f64) {
+        self.set_stop_target(decel);
+        self.decel_tracking = true;
+    }
+
+    /// Returns true if the target is set by
+    /// [set_decel_target](SpeedController::set_decel_target).
+    pub fn is_tracking_decel(&self) -> bool {
+        self.decel_tracking
     }
 
     pub fn step(&mut self, current_speed: f64) -> SpeedControl {
+        let speed_control_weight = self.speed_control_weight();
         let Self {
             ref mut speed_pid,
-            // ref mut accel_activator,
             target_speed,
             target_accel,
-            // min_accel,
+            max_speed,
             max_accel,
             max_decel,
+            overspeed_gain,
+            thresholds,
+            accel_mode,
+            accelerating_limit_scale,
+            braking_limit_scale,
+            speed_priority,
+            launch_accel,
+            launch_speed,
+            decel_tracking,
             ..
         } = *self;
 
-        let is_standing = current_speed.abs() < STAND_STILL_SPEED_MS;
-        let is_stopping = target_speed.abs() < FULL_STOP_SPEED_MS;
+        let is_standing = current_speed.abs() < thresholds.stand_still_speed;
+        let is_stopping = target_speed.abs() < thresholds.full_stop_speed;
         let is_full_stop = is_standing && is_stopping;
 
         let setpoint_speed = match (is_standing, is_stopping) {
@@ -116,22 +316,26 @@ impl SpeedController {
         };
 
         let target_accel_abs = target_accel.abs();
-        let is_inertial = target_accel_abs < INTERNAL_ACCEL_MS2;
-        // let is_speed_control_enabled = {
-        //     let is_accel_triggered = !is_inertial && target_accel_abs >= min_accel;
-
-        //     if is_accel_triggered {
-        //         accel_activator.inc()
-        //     } else {
-        //         accel_activator.dec();
-        //         false
-        //     }
-        // };
-        let is_speed_control_enabled = true;
-
-        let (setpoint_accel, delta_accel) = if is_speed_control_enabled {
+        let is_inertial = target_accel_abs < thresholds.inertial_accel;
+
+        // In setpoint mode, track the target accel until the target
+        // speed is reached.
+        let is_tracking_accel = (accel_mode == AccelMode::Setpoint || decel_tracking)
+            && !is_full_stop
+            && !is_inertial
+            && if target_accel > 0.0 {
+                current_speed.abs() < target_speed.abs()
+            } else {
+                current_speed.abs() > target_speed.abs()
+            };
+
+        let (setpoint_accel, delta_accel, pid_output) = if is_tracking_accel {
+            (target_accel, 0.0, None)
+        } else if speed_control_weight > 0.0 {
             speed_pid.setpoint = setpoint_speed.abs();
-            let delta = speed_pid.next_control_output(current_speed).output;
+            let pid_output = speed_pid.next_control_output(current_speed.abs());
+            self.speed_integral = pid_output.i;
+            let delta = pid_output.output;
 
             let (lower, upper) = if is_inertial {
                 (-max_decel, max_accel)
@@ -140,16 +344,87 @@ impl SpeedController {
             };
 
             let prev_target = if is_full_stop { 0.0 } else { target_accel };
+
+            // Scale the per-step delta limit by the driving regime
+            let limit_scale = if prev_target + delta < 0.0 {
+                braking_limit_scale
+            } else {
+                accelerating_limit_scale
+            };
+            let delta_limit = speed_pid.output_limit * limit_scale;
+            let delta = delta.clamp(-delta_limit, delta_limit);
+
             let target = (prev_target + delta).clamp(lower, upper);
-            (target, delta)
+
+            // Blend between the open loop and the speed PID output
+            let open_loop_weight = 1.0 - speed_control_weight;
+            (
+                speed_control_weight * target + open_loop_weight * target_accel,
+                speed_control_weight * delta,
+                Some(pid_output),
+            )
+        } else {
+            (target_accel, 0.0, None)
+        };
+
+        // Blend the speed loop with the target accel by priority
+        let setpoint_accel = if is_full_stop {
+            setpoint_accel
         } else {
-            (target_accel, 0.0)
+            speed_priority * setpoint_accel + (1.0 - speed_priority) * target_accel
+        };
+
+        // Pull away gently from a stop
+        let setpoint_accel = match launch_accel {
+            Some(launch_accel) if !is_stopping && current_speed.abs() < launch_speed => {
+                setpoint_accel.min(launch_accel)
+            }
+            _ => setpoint_accel,
+        };
+
+        // Brake down below max_speed if the vehicle runs too fast,
+        // regardless of the target.
+        let overspeed = current_speed.abs() - max_speed;
+        let setpoint_accel = if overspeed > 0.0 {
+            setpoint_accel.min(-(overspeed_gain * overspeed).min(max_decel))
+        } else {
+            setpoint_accel
         };
 
         SpeedControl {
             setpoint_accel,
             delta_accel,
             full_stop: is_full_stop,
+            pid_output,
+        }
+    }
+
+    /// Computes the weight of the speed PID in [0, 1] against the
+    /// open loop target accel.
+    ///
+    /// The speed PID is always fully engaged if `min_accel_gating` is
+    /// off or the target accel is inertial (no accel constraint).
+    fn speed_control_weight(&self) -> f64 {
+        let Self {
+            target_accel,
+            min_accel,
+            min_accel_gating,
+            min_accel_band,
+            thresholds,
+            ..
+        } = *self;
+        let target_accel_abs = target_accel.abs();
+        let is_inertial = target_accel_abs < thresholds.inertial_accel;
+
+        if !min_accel_gating || is_inertial {
+            1.0
+        } else if min_accel_band > 0.0 {
+            let lower = min_accel - min_accel_band / 2.0;
+            ((target_accel_abs - lower) / min_accel_band).clamp(0.0, 1.0)
+        } else if target_accel_abs >= min_accel {
+            1.0
+        } else {
+            0.0
         }
     }
 }
@@ -158,29 +433,89 @@ pub struct SpeedControl {
     pub setpoint_accel: f64,
     pub delta_accel: f64,
     pub full_stop: bool,
+    /// The raw speed PID output if the PID ran in this step.
+    pub pid_output: Option<ControlOutput<f64>>,
 }
 
-#[derive(Debug)]
-struct DelayedActivator {
-    max: usize,
-    cur: usize,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::VehicleClass;
 
-impl DelayedActivator {
-    pub fn new(max: usize) -> Self {
-        Self { max, cur: 0 }
+    fn sedan() -> SpeedControllerInit {
+        SpeedControllerInit::from_physics(&VehiclePhysics::preset(VehicleClass::Sedan), None)
     }
 
-    pub fn inc(&mut self) -> bool {
-        let Self { max, cur } = *self;
-        let next = if max == cur { max } else { cur + 1 };
-        self.cur = next;
-        next == max
+    #[test]
+    fn stopping_decel_replaces_target_accel() {
+        let mut init = sedan();
+        init.stopping_decel = Some(4.0);
+        let mut controller = init.build();
+        controller.set_target(0.0, 2.0);
+        assert!(controller.accel_overridden());
+        assert_eq!(controller.target_accel, -4.0);
+
+        controller.set_target(5.0, 2.0);
+        assert!(!controller.accel_overridden());
+        assert_eq!(controller.target_accel, 2.0);
     }
 
-    pub fn dec(&mut self) {
-        if let Some(next) = self.cur.checked_sub(1) {
-            self.cur = next;
-        }
+    #[test]
+    fn decel_target_is_tracked_in_limit_mode() {
+        let mut controller = sedan().build();
+        controller.set_decel_target(3.0);
+        assert!(controller.is_tracking_decel());
+
+        let control = controller.step(5.0);
+        assert_eq!(control.setpoint_accel, -3.0);
+        assert!(control.pid_output.is_none());
+        assert!(controller.step(0.05).full_stop);
+
+        controller.set_target(5.0, 1.0);
+        assert!(!controller.is_tracking_decel());
+    }
+
+    #[test]
+    fn launch_accel_caps_pulling_away() {
+        let mut init = sedan();
+        init.accel_mode = AccelMode::Setpoint;
+        init.launch_accel = Some(1.0);
+        let mut controller = init.build();
+        controller.set_target(10.0, 2.5);
+        assert_eq!(controller.step(0.5).setpoint_accel, 1.0);
+        assert_eq!(controller.step(3.0).setpoint_accel, 2.5);
+    }
+
+    #[test]
+    fn overspeed_brakes_regardless_of_target() {
+        let mut init = sedan();
+        init.accel_mode = AccelMode::Setpoint;
+        let mut controller = init.build();
+        let max_speed = controller.max_speed();
+        controller.set_target(max_speed, 2.0);
+        assert!(controller.step(max_speed + 2.0).setpoint_accel <= -2.0);
+    }
+
+    #[test]
+    fn min_accel_gating_applies_small_accel_open_loop() {
+        let mut init = sedan();
+        init.min_accel_gating = true;
+        let mut controller = init.build();
+        controller.set_target(10.0, 0.5);
+        let control = controller.step(5.0);
+        assert_eq!(control.setpoint_accel, 0.5);
+        assert!(control.pid_output.is_none());
+
+        controller.set_target(10.0, 1.5);
+        assert!(controller.step(5.0).pid_output.is_some());
+    }
+
+    #[test]
+    fn zero_speed_priority_follows_target_accel() {
+        let mut controller = sedan().build();
+        controller.set_speed_priority(-1.0);
+        assert_eq!(controller.speed_priority(), 0.0);
+        controller.set_target(10.0, 2.0);
+        assert_eq!(controller.step(5.0).setpoint_accel, 2.0);
     }
 }
@@ -1,9 +1,37 @@
 use crate::physics::VehiclePhysics;
 
-#[derive(Debug)]
+/// The sign convention of target steering angles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SteerConvention {
+    /// Positive angles steer to the left, e.g. ROS and ISO 8855.
+    LeftPositive,
+    /// Positive angles steer to the right like CARLA's steer command.
+    #[default]
+    RightPositive,
+}
+
+impl SteerConvention {
+    /// Converts an angle in this convention to the right-positive
+    /// convention of [SteerController] and CARLA.
+    pub fn to_right_positive(&self, steering_angle: f64) -> f64 {
+        match self {
+            Self::LeftPositive => -steering_angle,
+            Self::RightPositive => steering_angle,
+        }
+    }
+}
+
+/// The steering controller.
+///
+/// Positive steering angles steer to the right and negative angles to
+/// the left, following the sign of CARLA's steer command.
+#[derive(Debug, Clone)]
 pub struct SteerController {
     pub target_steering_angle: f64,
-    pub max_steering_angle: f64,
+    pub max_left_steering_angle: f64,
+    pub max_right_steering_angle: f64,
+    /// Set when the last target exceeded the steering limits.
+    pub saturated: bool,
 }
 
 impl SteerController {
@@ -12,18 +40,82 @@ impl SteerController {
     }
 
     pub fn new(max_steering_angle: f64) -> Self {
+        Self::new_asymmetric(max_steering_angle, max_steering_angle)
+    }
+
+    /// Creates a controller with different steering limits on each
+    /// side. Both limits are positive angles.
+    pub fn new_asymmetric(max_left_steering_angle: f64, max_right_steering_angle: f64) -> Self {
         Self {
-            max_steering_angle,
+            max_left_steering_angle,
+            max_right_steering_angle,
             target_steering_angle: 0.0,
+            saturated: false,
         }
     }
 
+    /// Gets the steering limit reachable on both sides.
+    #[deprecated(note = "use `max_left_steering_angle` and `max_right_steering_angle`")]
+    pub fn max_steering_angle(&self) -> f64 {
+        self.max_left_steering_angle
+            .min(self.max_right_steering_angle)
+    }
+
+    /// Sets the same steering limit on both sides.
+    #[deprecated(note = "set `max_left_steering_angle` and `max_right_steering_angle`")]
+    pub fn set_max_steering_angle(&mut self, max_steering_angle: f64) {
+        self.max_left_steering_angle = max_steering_angle;
+        self.max_right_steering_angle = max_steering_angle;
+    }
+
     pub fn set_target(&mut self, target_steering_angle: f64) {
-        let max = self.max_steering_angle;
-        self.target_steering_angle = target_steering_angle.clamp(-max, max);
+        let clamped = target_steering_angle
+            .clamp(-self.max_left_steering_angle, self.max_right_steering_angle);
+        self.saturated = clamped != target_steering_angle;
+        self.target_steering_angle = clamped;
     }
 
     pub fn steer_ratio(&self) -> f64 {
-        self.target_steering_angle / self.max_steering_angle
+        let target = self.target_steering_angle;
+        if target >= 0.0 {
+            target / self.max_right_steering_angle
+        } else {
+            target / self.max_left_steering_angle
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn left_positive_angles_are_negated() {
+        assert_eq!(SteerConvention::LeftPositive.to_right_positive(0.3), -0.3);
+        assert_eq!(SteerConvention::RightPositive.to_right_positive(0.3), 0.3);
+    }
+
+    #[test]
+    fn asymmetric_limits_clamp_and_normalize_per_side() {
+        let mut controller = SteerController::new_asymmetric(0.4, 0.5);
+        controller.set_target(-0.6);
+        assert!(controller.saturated);
+        assert_eq!(controller.steer_ratio(), -1.0);
+
+        controller.set_target(0.25);
+        assert!(!controller.saturated);
+        assert_eq!(controller.steer_ratio(), 0.5);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn symmetric_limit_accessors_cover_both_sides() {
+        let mut controller = SteerController::new_asymmetric(0.4, 0.5);
+        assert_eq!(controller.max_steering_angle(), 0.4);
+
+        controller.set_max_steering_angle(0.6);
+        assert_eq!(controller.max_left_steering_angle, 0.6);
+        assert_eq!(controller.max_right_steering_angle, 0.6);
+        assert_eq!(controller.max_steering_angle(), 0.6);
     }
 }
@@ -1,11 +1,33 @@
 use crate::{
     accel_control::{AccelControl, AccelController, AccelControllerInit},
-    constants::FULL_STOP_SPEED_MS,
-    physics::VehiclePhysics,
-    speed_control::{SpeedControl, SpeedController, SpeedControllerInit},
-    steer_control::SteerController,
+    constants::{Thresholds, MIN_SLIP_REFERENCE_SPEED_MS},
+    effort::{ControlEffort, Peaks},
+    filter::{JerkLimiter, LowPassFilter, RateLimiter},
+    instability::InstabilityDetector,
+    physics::{
+        gravity_compensated_accel, longitudinal_grip_budget, DragAreaEstimator, GradeEstimator,
+        ResistiveModel, VehicleClass, VehiclePhysics,
+    },
+    pid::{ControlOutput, PidChannel, PidHook, PidTerms},
+    sensors::{vehicle_pitch, vehicle_speed, VehicleSensors},
+    speed_control::{AccelMode, SpeedControl, SpeedController, SpeedControllerInit},
+    steer_control::{SteerController, SteerConvention},
 };
-use carla::rpc::VehiclePhysicsControl;
+use carla::{
+    client::{Vehicle, WorldSnapshot},
+    rpc::{VehicleControl, VehiclePhysicsControl},
+};
+use std::{error::Error, fmt};
+
+/// The half width (m/s²) of the band around the coasting accel, in
+/// which the vehicle coasts at full `efficiency_weight`.
+pub const EFFICIENCY_COAST_BAND_MS2: f64 = 0.3;
+/// The factor on the accel above the coasting accel at full
+/// `efficiency_weight`.
+pub const EFFICIENCY_ACCEL_SCALE: f64 = 0.5;
+/// The gain (1/s) converting the speed to extra deceleration while
+/// holding against creep.
+pub const CREEP_HOLD_GAIN: f64 = 1.0;
 
 /// Initializer of [VehicleController].
 #[derive(Debug, Clone)]
@@ -13,7 +35,209 @@ pub struct VehicleControllerInit {
     pub physics: VehiclePhysics,
     pub speed_controller: SpeedControllerInit,
     pub accel_controller: AccelControllerInit,
+    /// The speed and accel thresholds, passed down to the speed
+    /// controller in place of its own.
+    pub thresholds: Thresholds,
     pub max_steering_angle: f64,
+    /// Overrides `max_steering_angle` for left (negative) steering.
+    pub max_left_steering_angle: Option<f64>,
+    /// Overrides `max_steering_angle` for right (positive) steering.
+    pub max_right_steering_angle: Option<f64>,
+    /// If set, [VehicleController::try_step] validates the output and
+    /// returns an error on violation.
+    pub validate_output: bool,
+    /// Whether steering is applied while in [Status::FullStop].
+    pub steer_at_standstill: bool,
+    /// If set, the acceleration PID uses this integral gain while the
+    /// vehicle stands still out of full stop, so that it accumulates
+    /// enough pedal to prevent rollback on a slope.
+    pub standstill_integral_gain: Option<f64>,
+    /// The half width of the band around zero target speed (m/s), in
+    /// which the reverse flag keeps its previous state.
+    pub reverse_hysteresis: f64,
+    /// The number of steps after construction, during which the
+    /// acceleration estimate is suppressed and the acceleration PID is
+    /// held, so that the controller settles before acting.
+    pub startup_blanking_steps: usize,
+    /// The number of recent pedal deltas inspected for oscillation.
+    pub instability_window: usize,
+    /// The instability score in [0, 1], at which the report flags the
+    /// controller as unstable.
+    pub instability_threshold: f64,
+    /// If set, the hand brake is engaged when the brake reaches this
+    /// value in [0, 1] and the vehicle still speeds up, e.g. on a steep
+    /// descent.
+    pub descent_hand_brake_threshold: Option<f64>,
+    /// The width (m/s²) of the zone around the throttle and brake
+    /// borders, in which the output blends between the adjacent
+    /// regimes instead of snapping. Zero disables blending, and so do
+    /// border offsets that close the coasting gap.
+    pub pedal_blend_width: f64,
+    /// If set, the pitch input is smoothed by a low-pass filter with
+    /// this time constant in seconds.
+    pub pitch_filter_time_constant: Option<f64>,
+    /// If set, the measured speed is smoothed by a low-pass filter
+    /// with this time constant in seconds before it is used by the
+    /// speed controller and the acceleration estimate.
+    pub speed_filter_time_constant: Option<f64>,
+    /// The bound (m/s²) on the absolute acceleration estimated from
+    /// speed differences.
+    pub accel_estimate_limit: f64,
+    /// If set, the target speed is rounded to a multiple of this step
+    /// (m/s), so that changes below the step are ignored.
+    pub target_speed_step: Option<f64>,
+    /// If set, the target accel is rounded to a multiple of this step
+    /// (m/s²), so that changes below the step are ignored.
+    pub target_accel_step: Option<f64>,
+    /// The offset (m/s²) added to the throttle lower border. Positive
+    /// values bias the controller toward braking, negative values toward
+    /// throttle.
+    pub throttle_border_offset: f64,
+    /// The offset (m/s²) added to the brake upper border, like
+    /// `throttle_border_offset`.
+    pub brake_border_offset: f64,
+    /// If set, the report flags the speed input as stale after this
+    /// number of consecutive identical speed samples. Samples at full
+    /// stop are not counted since a standing vehicle reports a constant
+    /// speed. Fewer than two samples cannot repeat, so such values
+    /// disable the check.
+    pub stale_speed_samples: Option<usize>,
+    /// If set, [VehicleController::step_with_wheel_speeds] scales the
+    /// throttle down when the slip ratio exceeds this value.
+    pub max_slip_ratio: Option<f64>,
+    /// If unset, negative target speeds are clamped to zero and reverse
+    /// is never commanded.
+    pub allow_reverse: bool,
+    /// If set, the deceleration (m/s²) available at full brake near
+    /// standstill. The brake mapping blends linearly from the physics max
+    /// deceleration at `low_speed_decel_speed` to this value at zero
+    /// speed.
+    pub low_speed_max_decel: Option<f64>,
+    /// The speed (m/s) below which `low_speed_max_decel` takes effect.
+    pub low_speed_decel_speed: f64,
+    /// If set, the controller accumulates the [ControlEffort] of its
+    /// outputs, see [VehicleController::control_effort].
+    pub track_control_effort: bool,
+    /// If set, the jerk of the unified pedal `throttle - brake` is
+    /// limited to this value in 1/s³ for smoother outputs. The brake
+    /// holding the vehicle at full stop is not limited.
+    pub max_pedal_jerk: Option<f64>,
+    /// If positive, throttle and brake are computed independently and
+    /// may both be nonzero, e.g. for left-foot braking, as long as the
+    /// smaller one does not exceed this value in [0, 1]. Zero keeps them
+    /// strictly exclusive. The overlap is lost when `max_pedal_jerk` is
+    /// set, which limits the unified pedal.
+    pub max_pedal_overlap: f64,
+    /// The cap in [0, 1] on the absolute steer ratio of [Output::steer],
+    /// e.g. to simulate a degraded actuator. One keeps full authority.
+    pub steer_ratio_limit: f64,
+    /// If set, the road grade is estimated from the discrepancy between
+    /// the modeled and the measured acceleration, smoothed with this
+    /// time constant in seconds. See [GradeEstimator].
+    pub grade_estimator_time_constant: Option<f64>,
+    /// If set, the controller recomputes its output only once this many
+    /// seconds have elapsed over consecutive steps, like an ECU with a
+    /// fixed cycle time, and returns the held output in between.
+    pub control_period_sec: Option<f64>,
+    /// Biases the controller toward energy efficiency in [0, 1]. The
+    /// vehicle coasts while the setpoint accel is within
+    /// `efficiency_weight` times [EFFICIENCY_COAST_BAND_MS2] of the coasting
+    /// accel, and accelerations above the coasting accel are softened down
+    /// to [EFFICIENCY_ACCEL_SCALE] at 1. Zero disables the bias.
+    pub efficiency_weight: f64,
+    /// If set, the target speed moves toward the requested one at this
+    /// rate (m/s²) on every step instead of snapping on
+    /// [VehicleController::set_target]. It starts from the speed
+    /// measured on the first step.
+    pub target_speed_ramp_rate: Option<f64>,
+    /// If set, the target accel moves toward the requested one at this
+    /// rate (m/s³), like `target_speed_ramp_rate`.
+    pub target_accel_ramp_rate: Option<f64>,
+    /// If set, the target steering angle moves toward the requested one
+    /// at this rate (rad/s), like `target_speed_ramp_rate`.
+    pub steering_ramp_rate: Option<f64>,
+    /// If set, every step also produces a [VerboseReport] read by
+    /// [VehicleController::verbose_report].
+    pub verbose_report: bool,
+    /// If set, the positive setpoint accel is capped to the longitudinal
+    /// grip left on the friction circle after the lateral accel given by
+    /// [VehicleController::set_lateral_accel]. Braking is never capped.
+    pub friction_coefficient: Option<f64>,
+    /// If set, the pedal is frozen once the speed error stays below this
+    /// value (m/s) and the accel error below `steady_hold_accel_error` for
+    /// `steady_hold_dwell_sec`. It is released when the speed error
+    /// exceeds `steady_hold_release_speed_error`.
+    pub steady_hold_speed_error: Option<f64>,
+    /// The accel error (m/s²) below which the controller may hold the
+    /// pedal, see `steady_hold_speed_error`.
+    pub steady_hold_accel_error: f64,
+    /// The seconds of steady state before the pedal is held, see
+    /// `steady_hold_speed_error`.
+    pub steady_hold_dwell_sec: f64,
+    /// The speed error (m/s) releasing a held pedal, see
+    /// `steady_hold_speed_error`.
+    pub steady_hold_release_speed_error: f64,
+    /// The seconds the controller stays at full stop before engaging
+    /// the hand brake. The brake is fully applied in the meantime.
+    pub hand_brake_dwell_sec: f64,
+    /// If set, the output values are rounded to f32 precision, which is
+    /// what CARLA receives.
+    pub round_output_to_f32: bool,
+    /// The sign convention of [TargetRequest::steering_angle]. The
+    /// [Output::steer] always follows CARLA, where positive steers right.
+    pub steer_convention: SteerConvention,
+    /// If set, the setpoint accel is capped to this accel (m/s²) for
+    /// passenger comfort, overriding the physics limits.
+    pub comfort_accel_limit: Option<f64>,
+    /// If set, the setpoint accel is bounded below by minus this
+    /// deceleration (m/s²), like `comfort_accel_limit`. Full stops are
+    /// not limited.
+    pub comfort_decel_limit: Option<f64>,
+    /// If set, a [DragAreaEstimator] fits the drag area while coasting
+    /// above this speed (m/s) and updates the physics with it.
+    pub drag_estimator_min_speed: Option<f64>,
+    /// If set, the output carries a [Output::brake_bias] of this gain
+    /// times the yaw error given by
+    /// [VehicleController::set_yaw_correction].
+    pub yaw_correction_gain: Option<f64>,
+    /// The bound on the magnitude of [Output::brake_bias] in [0, 1].
+    pub max_brake_bias: f64,
+    /// The response curve applied to the throttle and the brake.
+    pub pedal_curve: PedalCurve,
+    /// If set, the accel (m/s²) by which the drivetrain creeps at idle.
+    /// At full stop, the controller then brakes just enough to hold the
+    /// vehicle against it instead of applying the full brake. The hand
+    /// brake still engages after `hand_brake_dwell_sec`.
+    pub creep_accel: Option<f64>,
+    /// If set, the gear changes only after the vehicle stood still for
+    /// this many seconds. The vehicle first brakes to a stop and is held
+    /// there meanwhile. If unset, the gear changes as soon as the
+    /// vehicle stands still.
+    pub reverse_dwell_sec: Option<f64>,
+    /// The deceleration (m/s²) at least applied while moving against
+    /// the direction of a pending gear change, which waits for the
+    /// vehicle to stand still, see `reverse_dwell_sec`.
+    pub gear_change_decel: f64,
+    /// The maximum brake in [0, 1] outside an
+    /// [emergency_stop](VehicleController::emergency_stop). The brake holding
+    /// the vehicle at full stop is not capped.
+    pub max_normal_brake: f64,
+    /// If set, a step longer than this many seconds is split into equal
+    /// sub-steps no longer than it, with the speed interpolated linearly
+    /// from the last step. The output of the last sub-step is returned.
+    pub max_substep_sec: Option<f64>,
+    /// If set, the accel passed to
+    /// [step_with_measured_accel](VehicleController::step_with_measured_accel)
+    /// is taken as the raw accelerometer reading, and the gravity component
+    /// along the slope is removed from it using the pitch. The accel
+    /// differenced from the speed is not affected since it has no gravity
+    /// component.
+    pub measured_accel_includes_gravity: bool,
+    /// If set, the gear changes at most once in this many seconds of the
+    /// controller clock. A gear change requested earlier is deferred until
+    /// the interval elapsed, and the vehicle is stopped and held meanwhile
+    /// like during `reverse_dwell_sec`.
+    pub min_reverse_interval_sec: Option<f64>,
 }
 
 impl VehicleControllerInit {
@@ -24,76 +248,659 @@ impl VehicleControllerInit {
         Self::from_physics(VehiclePhysics::new(physics_control), min_accel)
     }
 
+    /// Creates the config from the [VehiclePhysics::preset] of the
+    /// given class with PID gains tuned for it.
+    pub fn preset(class: VehicleClass) -> Self {
+        // (speed kp, speed kd, accel kp, accel kd)
+        let (speed_kp, speed_kd, accel_kp, accel_kd) = match class {
+            VehicleClass::Sedan => (0.05, 0.5, 0.05, 0.05),
+            VehicleClass::SUV => (0.05, 0.5, 0.04, 0.05),
+            VehicleClass::Truck => (0.05, 0.4, 0.03, 0.04),
+            VehicleClass::SportsCar => (0.08, 0.5, 0.06, 0.05),
+            VehicleClass::Motorcycle => (0.06, 0.4, 0.05, 0.04),
+        };
+
+        let mut init = Self::from_physics(VehiclePhysics::preset(class), None);
+        let speed_pid = &mut init.speed_controller.pid;
+        speed_pid.kp = speed_kp;
+        speed_pid.kd = speed_kd;
+        let accel_pid = &mut init.accel_controller.pid;
+        accel_pid.kp = accel_kp;
+        accel_pid.kd = accel_kd;
+        init
+    }
+
     pub fn from_physics(physics: VehiclePhysics, min_accel: Option<f64>) -> Self {
         Self {
             speed_controller: SpeedControllerInit::from_physics(&physics, min_accel),
             accel_controller: AccelControllerInit::from_physics(&physics),
+            thresholds: Thresholds::default(),
             max_steering_angle: physics.max_steering_angle(),
+            max_left_steering_angle: None,
+            max_right_steering_angle: None,
+            validate_output: false,
+            steer_at_standstill: true,
+            standstill_integral_gain: None,
+            reverse_hysteresis: 0.0,
+            startup_blanking_steps: 0,
+            instability_window: 20,
+            instability_threshold: 0.8,
+            descent_hand_brake_threshold: None,
+            pedal_blend_width: 0.0,
+            pitch_filter_time_constant: None,
+            speed_filter_time_constant: None,
+            accel_estimate_limit: 3.0 * physics.max_deceleration(),
+            target_speed_step: None,
+            target_accel_step: None,
+            throttle_border_offset: 0.0,
+            brake_border_offset: 0.0,
+            stale_speed_samples: None,
+            max_slip_ratio: None,
+            allow_reverse: true,
+            low_speed_max_decel: None,
+            low_speed_decel_speed: 3.0,
+            track_control_effort: false,
+            max_pedal_jerk: None,
+            max_pedal_overlap: 0.0,
+            steer_ratio_limit: 1.0,
+            grade_estimator_time_constant: None,
+            control_period_sec: None,
+            efficiency_weight: 0.0,
+            target_speed_ramp_rate: None,
+            target_accel_ramp_rate: None,
+            steering_ramp_rate: None,
+            verbose_report: false,
+            friction_coefficient: None,
+            steady_hold_speed_error: None,
+            steady_hold_accel_error: 0.1,
+            steady_hold_dwell_sec: 1.0,
+            steady_hold_release_speed_error: 0.5,
+            hand_brake_dwell_sec: 0.0,
+            round_output_to_f32: false,
+            steer_convention: SteerConvention::RightPositive,
+            comfort_accel_limit: None,
+            comfort_decel_limit: None,
+            drag_estimator_min_speed: None,
+            yaw_correction_gain: None,
+            max_brake_bias: 0.3,
+            pedal_curve: PedalCurve::Linear,
+            creep_accel: None,
+            reverse_dwell_sec: None,
+            gear_change_decel: 2.0,
+            max_normal_brake: 1.0,
+            max_substep_sec: None,
+            measured_accel_includes_gravity: false,
+            min_reverse_interval_sec: None,
             physics,
         }
     }
 
+    /// Computes the target pedal at which the controller outputs
+    /// `throttle` at `speed` on a road with `pitch_radians`.
+    ///
+    /// Given the measured equilibrium throttle at a cruise speed, e.g.
+    /// from [characterize](crate::calibration::characterize), the
+    /// result seeds [AccelControllerInit::initial_target_pedal].
+    pub fn target_pedal_for_throttle(&self, throttle: f64, speed: f64, pitch_radians: f64) -> f64 {
+        let reverse = speed < 0.0;
+        let (throttle_lower_border, _) = pedal_borders(
+            &self.physics,
+            None,
+            (self.throttle_border_offset, self.brake_border_offset),
+            speed.abs(),
+            pitch_radians,
+            reverse,
+        );
+        throttle_lower_border + throttle.clamp(0.0, 1.0) * self.accel_controller.max_pedal
+    }
+
     pub fn build(self) -> VehicleController {
         let Self {
             physics,
             speed_controller,
             accel_controller,
+            thresholds,
             max_steering_angle,
+            max_left_steering_angle,
+            max_right_steering_angle,
+            validate_output,
+            steer_at_standstill,
+            standstill_integral_gain,
+            reverse_hysteresis,
+            startup_blanking_steps,
+            instability_window,
+            instability_threshold,
+            descent_hand_brake_threshold,
+            pedal_blend_width,
+            pitch_filter_time_constant,
+            speed_filter_time_constant,
+            accel_estimate_limit,
+            target_speed_step,
+            target_accel_step,
+            throttle_border_offset,
+            brake_border_offset,
+            stale_speed_samples,
+            max_slip_ratio,
+            allow_reverse,
+            low_speed_max_decel,
+            low_speed_decel_speed,
+            track_control_effort,
+            max_pedal_jerk,
+            max_pedal_overlap,
+            steer_ratio_limit,
+            grade_estimator_time_constant,
+            control_period_sec,
+            efficiency_weight,
+            target_speed_ramp_rate,
+            target_accel_ramp_rate,
+            steering_ramp_rate,
+            verbose_report,
+            friction_coefficient,
+            steady_hold_speed_error,
+            steady_hold_accel_error,
+            steady_hold_dwell_sec,
+            steady_hold_release_speed_error,
+            hand_brake_dwell_sec,
+            round_output_to_f32,
+            steer_convention,
+            comfort_accel_limit,
+            comfort_decel_limit,
+            drag_estimator_min_speed,
+            yaw_correction_gain,
+            max_brake_bias,
+            pedal_curve,
+            creep_accel,
+            reverse_dwell_sec,
+            gear_change_decel,
+            max_normal_brake,
+            max_substep_sec,
+            measured_accel_includes_gravity,
+            min_reverse_interval_sec,
         } = self;
 
         VehicleController {
-            measurement: Measurement::default(),
+            measurement: Measurement {
+                speed_filter: speed_filter_time_constant.map(LowPassFilter::new),
+                accel_limit: accel_estimate_limit,
+                stale_samples: stale_speed_samples,
+                ..Measurement::default()
+            },
             physics,
-            speed_controller: speed_controller.build(),
+            speed_controller: SpeedControllerInit {
+                thresholds,
+                ..speed_controller
+            }
+            .build(),
             accel_controller: accel_controller.build(),
-            steer_controller: SteerController::new(max_steering_angle),
+            thresholds,
+            steer_controller: SteerController::new_asymmetric(
+                max_left_steering_angle.unwrap_or(max_steering_angle),
+                max_right_steering_angle.unwrap_or(max_steering_angle),
+            ),
+            validate_output,
+            steer_at_standstill,
+            standstill_integral_gain,
+            reverse_hysteresis,
+            reverse: false,
+            blanking_steps: startup_blanking_steps,
+            instability: InstabilityDetector::new(instability_window, instability_threshold),
+            descent_hand_brake_threshold,
+            resistive_model: None,
+            pedal_blend_width,
+            neutral: false,
+            pitch_filter: pitch_filter_time_constant.map(LowPassFilter::new),
+            target_speed_step,
+            target_accel_step,
+            throttle_border_offset,
+            brake_border_offset,
+            max_slip_ratio,
+            allow_reverse,
+            low_speed_max_decel,
+            low_speed_decel_speed,
+            control_effort: track_control_effort.then(ControlEffort::default),
+            pedal_jerk_limiter: max_pedal_jerk.map(JerkLimiter::new),
+            requested_accel: 0.0,
+            lateral_accel: 0.0,
+            decel_tracking: false,
+            emergency_stopping: false,
+            peaks: Peaks::default(),
+            gear_dwell_time_sec: 0.0,
+            last_gear_change_time_sec: None,
+            yaw_error: 0.0,
+            first_step: true,
+            steady_hold_time_sec: 0.0,
+            steady_holding: false,
+            steady_hold_pedals: None,
+            full_stop_time_sec: 0.0,
+            speed_profile: None,
+            max_pedal_overlap,
+            pid_hook: HookSlot(None),
+            steer_ratio_limit,
+            grade_estimator: grade_estimator_time_constant.map(GradeEstimator::new),
+            control_period_sec,
+            pending_time_sec: 0.0,
+            held_step: None,
+            last_step: None,
+            efficiency_weight,
+            speed_ramp: target_speed_ramp_rate.map(RateLimiter::new),
+            accel_ramp: target_accel_ramp_rate.map(RateLimiter::new),
+            steering_ramp: steering_ramp_rate.map(RateLimiter::new),
+            requested_speed: 0.0,
+            requested_steering_angle: 0.0,
+            stop_decel: None,
+            verbose_report: verbose_report.then(VerboseReport::default),
+            friction_coefficient,
+            steady_hold_speed_error,
+            steady_hold_accel_error,
+            steady_hold_dwell_sec,
+            steady_hold_release_speed_error,
+            hand_brake_dwell_sec,
+            round_output_to_f32,
+            steer_convention,
+            comfort_accel_limit,
+            comfort_decel_limit,
+            drag_estimator: drag_estimator_min_speed.map(DragAreaEstimator::new),
+            yaw_correction_gain,
+            max_brake_bias,
+            pedal_curve,
+            creep_accel,
+            reverse_dwell_sec,
+            gear_change_decel,
+            max_normal_brake,
+            max_substep_sec,
+            measured_accel_includes_gravity,
+            min_reverse_interval_sec,
         }
     }
 }
 
 /// A controller that controls the speed and steering of a vehicle.
-#[derive(Debug)]
+///
+/// # Determinism
+/// The controller uses no randomness and no wall clock. Its time only
+/// advances by the `time_delta_sec` passed to each step. Two
+/// controllers built from equal [VehicleControllerInit]s and fed
+/// identical sequences of targets and step inputs produce bit-identical
+/// [Output] and [Report] streams, e.g. for [replay](VehicleController::replay)
+/// based regression tests.
+///
+/// A clone continues from the same state, but it does not carry the
+/// hook registered by [on_pid_output](VehicleController::on_pid_output).
+#[derive(Debug, Clone)]
 pub struct VehicleController {
     measurement: Measurement,
     physics: VehiclePhysics,
     speed_controller: SpeedController,
     accel_controller: AccelController,
+    thresholds: Thresholds,
     steer_controller: SteerController,
+    validate_output: bool,
+    steer_at_standstill: bool,
+    standstill_integral_gain: Option<f64>,
+    reverse_hysteresis: f64,
+    reverse: bool,
+    blanking_steps: usize,
+    instability: InstabilityDetector,
+    descent_hand_brake_threshold: Option<f64>,
+    resistive_model: Option<ResistiveModel>,
+    pedal_blend_width: f64,
+    neutral: bool,
+    pitch_filter: Option<LowPassFilter>,
+    target_speed_step: Option<f64>,
+    target_accel_step: Option<f64>,
+    throttle_border_offset: f64,
+    brake_border_offset: f64,
+    max_slip_ratio: Option<f64>,
+    allow_reverse: bool,
+    low_speed_max_decel: Option<f64>,
+    low_speed_decel_speed: f64,
+    control_effort: Option<ControlEffort>,
+    pedal_jerk_limiter: Option<JerkLimiter>,
+    requested_accel: f64,
+    lateral_accel: f64,
+    decel_tracking: bool,
+    emergency_stopping: bool,
+    peaks: Peaks,
+    gear_dwell_time_sec: f64,
+    last_gear_change_time_sec: Option<f64>,
+    yaw_error: f64,
+    first_step: bool,
+    steady_hold_time_sec: f64,
+    steady_holding: bool,
+    steady_hold_pedals: Option<(f64, f64)>,
+    full_stop_time_sec: f64,
+    speed_profile: Option<Vec<(f64, f64)>>,
+    max_pedal_overlap: f64,
+    pid_hook: HookSlot,
+    steer_ratio_limit: f64,
+    grade_estimator: Option<GradeEstimator>,
+    control_period_sec: Option<f64>,
+    pending_time_sec: f64,
+    held_step: Option<(Output, Report)>,
+    last_step: Option<(Output, Report)>,
+    efficiency_weight: f64,
+    speed_ramp: Option<RateLimiter>,
+    accel_ramp: Option<RateLimiter>,
+    steering_ramp: Option<RateLimiter>,
+    requested_speed: f64,
+    requested_steering_angle: f64,
+    stop_decel: Option<f64>,
+    verbose_report: Option<VerboseReport>,
+    friction_coefficient: Option<f64>,
+    steady_hold_speed_error: Option<f64>,
+    steady_hold_accel_error: f64,
+    steady_hold_dwell_sec: f64,
+    steady_hold_release_speed_error: f64,
+    hand_brake_dwell_sec: f64,
+    round_output_to_f32: bool,
+    steer_convention: SteerConvention,
+    comfort_accel_limit: Option<f64>,
+    comfort_decel_limit: Option<f64>,
+    drag_estimator: Option<DragAreaEstimator>,
+    yaw_correction_gain: Option<f64>,
+    max_brake_bias: f64,
+    pedal_curve: PedalCurve,
+    creep_accel: Option<f64>,
+    reverse_dwell_sec: Option<f64>,
+    gear_change_decel: f64,
+    max_normal_brake: f64,
+    max_substep_sec: Option<f64>,
+    measured_accel_includes_gravity: bool,
+    min_reverse_interval_sec: Option<f64>,
+}
+
+/// Holds the PID output hook, which is dropped on clone.
+#[derive(Debug)]
+struct HookSlot(Option<PidHook>);
+
+impl Clone for HookSlot {
+    fn clone(&self) -> Self {
+        Self(None)
+    }
 }
 
 /// Desired target values passed to [VehicleController].
 #[derive(Debug, Clone)]
 pub struct TargetRequest {
+    /// The steering angle in radians, positive to the right unless
+    /// `steer_convention` in [VehicleControllerInit] says otherwise.
     pub steering_angle: f64,
     pub speed: f64,
     pub accel: f64,
 }
 
-/// The report created by [VehicleController::step].
+/// An input tick replayed by [VehicleController::replay].
 #[derive(Debug, Clone)]
+pub struct ReplayStep {
+    pub time_delta_sec: f64,
+    pub current_speed: f64,
+    pub pitch_radians: f64,
+    pub target: TargetRequest,
+}
+
+/// The report created by [VehicleController::step].
+#[derive(Debug, Clone, PartialEq)]
 pub struct Report {
     pub status: Status,
     pub setpoint_accel: f64,
     pub target_pedal: f64,
     pub delta_accel: f64,
     pub pedal_delta: f64,
+    /// The fraction of sign changes in recent pedal deltas in [0, 1].
+    pub instability_score: f64,
+    /// Set when the instability score reaches the configured threshold.
+    pub unstable: bool,
+    /// Set when the hand brake is engaged because the saturated brake
+    /// cannot hold the vehicle on a descent.
+    pub descent_hand_brake: bool,
+    /// How close the controller is to saturating in [0, 1], taking
+    /// the maximum over the speed PID output, the throttle and brake
+    /// commands and the steering.
+    pub authority_utilization: f64,
+    /// The commanded total wheel torque in Nm, computed from the
+    /// setpoint accel as `mass * setpoint_accel * wheel_radius`.
+    pub wheel_torque: f64,
+    /// Set when the speed input repeated for `stale_speed_samples`
+    /// samples, which hints at a stuck sensor.
+    pub input_stale: bool,
+    /// The longitudinal slip ratio, only reported by
+    /// [VehicleController::step_with_wheel_speeds].
+    pub slip_ratio: Option<f64>,
+    /// Set when the target steering angle exceeds the steering limits
+    /// and is clamped.
+    pub steering_saturated: bool,
+    /// The road grade in radians estimated by the [GradeEstimator] if
+    /// enabled.
+    pub estimated_grade_radians: Option<f64>,
+    /// Set on the first step, whose accel estimate starts from zero
+    /// speed and may be spurious, so the output is often clipped.
+    pub first_step: bool,
+    /// Set when the setpoint accel is below the accel reachable at full
+    /// brake on the current grade, so the vehicle cannot decelerate
+    /// as commanded regardless of the configuration.
+    pub decel_authority_exceeded: bool,
+    /// Set while the pedal is held at steady state, see
+    /// `steady_hold_speed_error` in [VehicleControllerInit].
+    pub steady_hold: bool,
+    /// The lag of the steering angle behind the target, see
+    /// [VehicleController::steering_tracking_error].
+    pub steering_tracking_error: f64,
+}
+
+/// The detailed report of the last step, produced only if
+/// `verbose_report` is enabled in [VehicleControllerInit].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerboseReport {
+    /// The measured speed in m/s after filtering.
+    pub measured_speed: f64,
+    /// The acceleration in m/s² estimated from the measured speed.
+    pub measured_accel: f64,
+    /// The pitch in radians after filtering.
+    pub pitch_radians: f64,
+    pub throttle_lower_border: f64,
+    pub brake_upper_border: f64,
+    /// The speed PID terms if the PID ran.
+    pub speed_pid: Option<PidTerms>,
+    /// The acceleration PID terms if the PID ran.
+    pub accel_pid: Option<PidTerms>,
+    /// The resistive force in N at the measured speed.
+    pub resistive_force: f64,
+    /// The commanded force in N, i.e. mass times setpoint accel.
+    pub commanded_force: f64,
+    pub steering_saturated: bool,
+    pub unstable: bool,
+}
+
+/// A response curve mapping a pedal command in [0, 1] to the
+/// emitted pedal in [0, 1].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PedalCurve {
+    Linear,
+    /// Squares the command for a finer low end.
+    Quadratic,
+    /// Interpolates linearly between `(command, pedal)` points sorted
+    /// by command, holding the nearest pedal outside. An empty table
+    /// is linear. Use [PedalCurve::lookup] to sort and validate the
+    /// table.
+    Lookup(Vec<(f64, f64)>),
+}
+
+impl PedalCurve {
+    /// Creates a [PedalCurve::Lookup] from `(command, pedal)` points in
+    /// any order, rejecting non-finite points and repeated commands.
+    pub fn lookup(mut table: Vec<(f64, f64)>) -> Result<Self, PedalCurveError> {
+        if let Some(&(command, pedal)) = table
+            .iter()
+            .find(|(command, pedal)| !(command.is_finite() && pedal.is_finite()))
+        {
+            return Err(PedalCurveError::NotFinite { command, pedal });
+        }
+        table.sort_by(|(lhs, _), (rhs, _)| lhs.total_cmp(rhs));
+        if let Some(pair) = table.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(PedalCurveError::RepeatedCommand(pair[0].0));
+        }
+        Ok(Self::Lookup(table))
+    }
+
+    pub fn apply(&self, command: f64) -> f64 {
+        let pedal = match self {
+            Self::Linear => command,
+            Self::Quadratic => command.powi(2),
+            Self::Lookup(table) if table.is_empty() => command,
+            Self::Lookup(table) => interpolate_profile(table, command),
+        };
+        pedal.clamp(0.0, 1.0)
+    }
 }
 
 /// Output of [VehicleController::step].
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Output {
     pub throttle: f64,
     pub brake: f64,
     pub steer: f64,
     pub reverse: bool,
     pub hand_brake: bool,
+    /// The left/right split of the brake in [-1, 1] for differential
+    /// braking, where positive values brake the right wheels harder
+    /// to yaw the vehicle to the right. It is `None` unless
+    /// `yaw_correction_gain` is set in [VehicleControllerInit].
+    pub brake_bias: Option<f64>,
+}
+
+impl Output {
+    /// Combines throttle and brake into a single pedal in [-1, 1],
+    /// where positive values are throttle and negative values are
+    /// brake.
+    pub fn as_unified_pedal(&self) -> f64 {
+        self.throttle - self.brake
+    }
+
+    /// Rounds the throttle, brake, steer and brake bias to the nearest
+    /// f32, so that they round-trip through f32 unchanged.
+    pub fn round_to_f32(&self) -> Self {
+        let round = |value: f64| value as f32 as f64;
+        Self {
+            throttle: round(self.throttle),
+            brake: round(self.brake),
+            steer: round(self.steer),
+            brake_bias: self.brake_bias.map(round),
+            ..self.clone()
+        }
+    }
+
+    /// Splits a unified pedal in [-1, 1] into `(throttle, brake)`.
+    ///
+    /// It is the inverse of [as_unified_pedal](Output::as_unified_pedal)
+    /// for outputs where throttle and brake are not both nonzero.
+    pub fn split_unified_pedal(pedal: f64) -> (f64, f64) {
+        if pedal > 0.0 {
+            (pedal, 0.0)
+        } else {
+            (0.0, -pedal)
+        }
+    }
+
+    /// Checks that throttle and brake are within [0, 1], at most one
+    /// of them is nonzero, steer is within [-1, 1], and no value is NaN.
+    pub fn validate(&self) -> Result<(), OutputError> {
+        self.validate_with_overlap(0.0)
+    }
+
+    /// Checks the output like [validate](Output::validate), but allows
+    /// both throttle and brake to be nonzero while the smaller one
+    /// does not exceed `max_overlap`.
+    pub fn validate_with_overlap(&self, max_overlap: f64) -> Result<(), OutputError> {
+        let Self {
+            throttle,
+            brake,
+            steer,
+            ..
+        } = *self;
+
+        if !(throttle.is_finite() && brake.is_finite() && steer.is_finite()) {
+            return Err(OutputError::NotFinite);
+        }
+        if !(0.0..=1.0).contains(&throttle) {
+            return Err(OutputError::ThrottleOutOfRange(throttle));
+        }
+        if !(0.0..=1.0).contains(&brake) {
+            return Err(OutputError::BrakeOutOfRange(brake));
+        }
+        if !(-1.0..=1.0).contains(&steer) {
+            return Err(OutputError::SteerOutOfRange(steer));
+        }
+        if throttle > 0.0 && brake > 0.0 && throttle.min(brake) > max_overlap {
+            return Err(OutputError::ThrottleAndBrake { throttle, brake });
+        }
+
+        Ok(())
+    }
+}
+
+/// The error returned when an [Output] fails validation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputError {
+    NotFinite,
+    ThrottleOutOfRange(f64),
+    BrakeOutOfRange(f64),
+    SteerOutOfRange(f64),
+    ThrottleAndBrake { throttle: f64, brake: f64 },
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotFinite => write!(f, "output contains non-finite values"),
+            Self::ThrottleOutOfRange(throttle) => {
+                write!(f, "throttle {throttle} is out of range [0, 1]")
+            }
+            Self::BrakeOutOfRange(brake) => write!(f, "brake {brake} is out of range [0, 1]"),
+            Self::SteerOutOfRange(steer) => write!(f, "steer {steer} is out of range [-1, 1]"),
+            Self::ThrottleAndBrake { throttle, brake } => {
+                write!(f, "throttle {throttle} and brake {brake} are both nonzero")
+            }
+        }
+    }
+}
+
+impl Error for OutputError {}
+
+/// The error returned by [PedalCurve::lookup] for an invalid table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PedalCurveError {
+    NotFinite { command: f64, pedal: f64 },
+    RepeatedCommand(f64),
+}
+
+impl fmt::Display for PedalCurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotFinite { command, pedal } => {
+                write!(f, "pedal curve point ({command}, {pedal}) is not finite")
+            }
+            Self::RepeatedCommand(command) => {
+                write!(f, "pedal curve command {command} is repeated")
+            }
+        }
+    }
 }
 
+impl Error for PedalCurveError {}
+
 #[derive(Debug, Clone)]
 struct Measurement {
     pub time_sec: f64,
     pub speed: f64,
     pub accel: f64,
+    pub speed_filter: Option<LowPassFilter>,
+    pub accel_limit: f64,
+    pub stale_samples: Option<usize>,
+    pub last_input_speed: Option<f64>,
+    pub repeat_count: usize,
+    /// The externally measured accel used instead of the finite
+    /// difference in the next update.
+    pub accel_override: Option<f64>,
 }
 
 /// The status reported by [VehicleController].
@@ -105,26 +912,128 @@ pub enum Status {
     Braking,
 }
 
+impl Status {
+    /// Gets the stable numeric code of the status.
+    ///
+    /// The mapping is `FullStop = 0`, `Accelerating = 1`,
+    /// `Coasting = 2` and `Braking = 3`. It does not depend on the
+    /// declaration order and will not change.
+    pub fn as_code(&self) -> u8 {
+        match self {
+            Self::FullStop => 0,
+            Self::Accelerating => 1,
+            Self::Coasting => 2,
+            Self::Braking => 3,
+        }
+    }
+
+    /// Parses a code created by [as_code](Status::as_code), returning
+    /// `None` for unknown codes.
+    pub fn from_code(code: u8) -> Option<Self> {
+        let status = match code {
+            0 => Self::FullStop,
+            1 => Self::Accelerating,
+            2 => Self::Coasting,
+            3 => Self::Braking,
+            _ => return None,
+        };
+        Some(status)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::FullStop => "FullStop",
+            Self::Accelerating => "Accelerating",
+            Self::Coasting => "Coasting",
+            Self::Braking => "Braking",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            throttle,
+            brake,
+            steer,
+            reverse,
+            hand_brake,
+            brake_bias,
+        } = *self;
+        write!(f, "thr={throttle:.2} brk={brake:.2} steer={steer:.2}")?;
+        if let Some(brake_bias) = brake_bias {
+            write!(f, " bias={brake_bias:.2}")?;
+        }
+        if reverse {
+            write!(f, " rev")?;
+        }
+        if hand_brake {
+            write!(f, " hb")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] a*={:.2} pedal={:.2}",
+            self.status, self.setpoint_accel, self.target_pedal
+        )?;
+        if self.unstable {
+            write!(f, " unstable")?;
+        }
+        Ok(())
+    }
+}
+
 impl Measurement {
-    pub fn update(&mut self, time_delta_sec: f64, current_speed: f64) {
-        let speed_delta = current_speed - self.speed;
-        let current_accel = speed_delta / time_delta_sec;
-        let time_sec = self.time_sec + time_delta_sec;
-        let is_full_stop = current_speed < FULL_STOP_SPEED_MS;
-
-        *self = if is_full_stop {
-            Measurement {
-                time_sec,
-                speed: 0.0,
-                accel: 0.0,
-            }
+    /// Records the current speed and returns it, smoothed by the speed
+    /// filter if present.
+    pub fn update(&mut self, time_delta_sec: f64, current_speed: f64, full_stop_speed: f64) -> f64 {
+        let is_repeated =
+            self.last_input_speed == Some(current_speed) && current_speed.abs() >= full_stop_speed;
+        self.repeat_count = if is_repeated {
+            self.repeat_count + 1
         } else {
-            Measurement {
-                time_sec,
-                speed: current_speed,
-                accel: current_accel,
+            0
+        };
+        self.last_input_speed = Some(current_speed);
+
+        let current_speed = match &mut self.speed_filter {
+            Some(filter) => filter.update(current_speed, time_delta_sec),
+            None => current_speed,
+        };
+        let current_accel = match self.accel_override.take() {
+            Some(accel) => accel,
+            None => {
+                let speed_delta = current_speed - self.speed;
+                (speed_delta / time_delta_sec).clamp(-self.accel_limit, self.accel_limit)
             }
         };
+        let is_full_stop = current_speed < full_stop_speed;
+
+        self.time_sec += time_delta_sec;
+        (self.speed, self.accel) = if is_full_stop {
+            (0.0, 0.0)
+        } else {
+            (current_speed, current_accel)
+        };
+
+        current_speed
+    }
+
+    /// Returns true if the speed input repeated for the configured
+    /// number of samples.
+    pub fn is_stale(&self) -> bool {
+        match self.stale_samples {
+            Some(samples) if samples >= 2 => self.repeat_count + 1 >= samples,
+            _ => false,
+        }
     }
 }
 
@@ -134,6 +1043,12 @@ impl Default for Measurement {
             time_sec: 0.0,
             speed: 0.0,
             accel: 0.0,
+            speed_filter: None,
+            accel_limit: f64::INFINITY,
+            stale_samples: None,
+            last_input_speed: None,
+            repeat_count: 0,
+            accel_override: None,
         }
     }
 }
@@ -152,33 +1067,798 @@ impl VehicleController {
 
     /// Creates a controller from an [VehiclePhysics] object.
     pub fn from_physics(physics: VehiclePhysics, min_accel: Option<f64>) -> Self {
-        VehicleControllerInit {
-            speed_controller: SpeedControllerInit::from_physics(&physics, min_accel),
-            accel_controller: AccelControllerInit::from_physics(&physics),
-            max_steering_angle: physics.max_steering_angle(),
-            physics,
+        VehicleControllerInit::from_physics(physics, min_accel).build()
+    }
+
+    /// Computes the maximum speed to traverse a curve without
+    /// exceeding the lateral acceleration limit.
+    ///
+    /// # Parameters
+    /// - `radius_m` is the curve radius in meters.
+    /// - `max_lateral_accel` is the lateral acceleration limit in m/s².
+    pub fn max_corner_speed(radius_m: f64, max_lateral_accel: f64) -> f64 {
+        (max_lateral_accel * radius_m).sqrt()
+    }
+
+    /// Gets the vehicle physics used by the controller.
+    pub fn physics(&self) -> &VehiclePhysics {
+        &self.physics
+    }
+
+    /// Changes the vehicle mass on the fly, e.g. when a trailer is
+    /// hitched or detached.
+    pub fn set_mass(&mut self, mass: f64) {
+        self.physics.set_mass(mass);
+    }
+
+    /// Replaces the physics model on the fly, e.g. every step with a
+    /// [VehiclePhysics::lerp] blend.
+    ///
+    /// The speed, accel and steering limits the controllers were built
+    /// with are kept.
+    pub fn set_physics(&mut self, physics: VehiclePhysics) {
+        self.physics = physics;
+    }
+
+    /// Changes the aerodynamic drag area (m²) on the fly.
+    pub fn set_drag_area(&mut self, drag_area: f64) {
+        self.physics.set_drag_area(drag_area);
+    }
+
+    /// Computes the open-loop pedal that produces `target_accel` at
+    /// `speed` on a road with `pitch_radians`, bypassing the PIDs.
+    ///
+    /// The pedal is normalized to [-1, 1], where positive values are
+    /// throttle, negative values are brake and zero is coasting. A
+    /// negative `speed` means driving in reverse.
+    pub fn pedal_for_accel(&self, target_accel: f64, speed: f64, pitch_radians: f64) -> f64 {
+        let reverse = speed < 0.0;
+        let (throttle_lower_border, brake_upper_border) = pedal_borders(
+            &self.physics,
+            self.resistive_model.as_ref(),
+            (self.throttle_border_offset, self.brake_border_offset),
+            speed.abs(),
+            pitch_radians,
+            reverse,
+        );
+        let max_pedal = self.accel_controller.max_pedal();
+        let max_brake_pedal = max_brake_pedal(
+            max_pedal,
+            self.physics.max_deceleration(),
+            (self.low_speed_max_decel, self.low_speed_decel_speed),
+            speed.abs(),
+        );
+
+        if target_accel > throttle_lower_border {
+            ((target_accel - throttle_lower_border) / max_pedal).min(1.0)
+        } else if target_accel > brake_upper_border {
+            0.0
+        } else {
+            let brake_ratio = ((brake_upper_border - target_accel) / max_brake_pedal).min(1.0);
+            -self.accel_controller.brake_command(brake_ratio)
+        }
+    }
+
+    /// Computes the `(min, max)` net accel in m/s² the controller can
+    /// command at `speed` on a road with `pitch_radians`.
+    ///
+    /// The bounds are the accels at full throttle and at the brake
+    /// allowed by `max_normal_brake` on the grade, limited by the speed
+    /// controller limits and the comfort limits. The max accel is also
+    /// limited by the friction circle if `friction_coefficient` is set
+    /// and by the `launch_accel` of the speed controller below its
+    /// `launch_speed`.
+    /// A negative `speed` means driving in reverse.
+    pub fn effective_accel_limits(&self, speed: f64, pitch_radians: f64) -> (f64, f64) {
+        let reverse = speed < 0.0;
+        let (throttle_lower_border, brake_upper_border) = pedal_borders(
+            &self.physics,
+            self.resistive_model.as_ref(),
+            (self.throttle_border_offset, self.brake_border_offset),
+            speed.abs(),
+            pitch_radians,
+            reverse,
+        );
+        let max_pedal = self.accel_controller.max_pedal();
+        let max_brake_pedal = max_brake_pedal(
+            max_pedal,
+            self.physics.max_deceleration(),
+            (self.low_speed_max_decel, self.low_speed_decel_speed),
+            speed.abs(),
+        );
+
+        // The brake ratio at which the brake command reaches the cap
+        let brake_intercept = self.accel_controller.brake_intercept();
+        let max_normal_brake = self.max_normal_brake.clamp(0.0, 1.0);
+        let max_brake_ratio = if brake_intercept < 1.0 {
+            ((max_normal_brake - brake_intercept) / (1.0 - brake_intercept)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let max_accel = (throttle_lower_border + max_pedal).min(self.speed_controller.max_accel());
+        let min_accel = (brake_upper_border - max_brake_ratio * max_brake_pedal)
+            .max(-self.speed_controller.max_decel());
+        let max_accel = match self.speed_controller.launch_accel() {
+            Some(launch_accel) if speed.abs() < self.speed_controller.launch_speed() => {
+                max_accel.min(launch_accel)
+            }
+            _ => max_accel,
+        };
+        let max_accel = self
+            .longitudinal_grip_budget()
+            .map_or(max_accel, |budget| max_accel.min(budget));
+        let max_accel = self
+            .comfort_accel_limit
+            .map_or(max_accel, |limit| max_accel.min(limit));
+        let min_accel = self
+            .comfort_decel_limit
+            .map_or(min_accel, |limit| min_accel.max(-limit));
+        (min_accel.min(max_accel), max_accel)
+    }
+
+    /// Computes the steady pedal in [-1, 1] that holds `speed` on a
+    /// road with `pitch_radians`, e.g. to warm start a cruise.
+    ///
+    /// It is the [pedal_for_accel](VehicleController::pedal_for_accel)
+    /// of zero net accel, so it is a brake on descents steep enough to
+    /// accelerate the coasting vehicle. The pitch follows the sign
+    /// convention of [VehiclePhysics::driving_impedance_acceleration].
+    pub fn cruise_pedal(&self, speed: f64, pitch_radians: f64) -> f64 {
+        self.pedal_for_accel(0.0, speed, pitch_radians)
+    }
+
+    /// Gets the longitudinal force in Newtons commanded by the last
+    /// [step](VehicleController::step), that is, the vehicle mass
+    /// times the setpoint acceleration.
+    pub fn commanded_force(&self) -> f64 {
+        self.physics.mass() * self.accel_controller.target_accel()
+    }
+
+    /// Gets the total wheel torque in Nm commanded by the last
+    /// [step](VehicleController::step), for torque-based interfaces.
+    pub fn commanded_wheel_torque(&self) -> f64 {
+        self.commanded_force() * self.physics.wheel_radius()
+    }
+
+    /// Computes the resistive force in Newtons at `speed` on a road
+    /// with `pitch_radians` in the current driving direction.
+    ///
+    /// See [VehiclePhysics::driving_impedance_force].
+    pub fn resistive_force(&self, speed: f64, pitch_radians: f64) -> f64 {
+        match &self.resistive_model {
+            Some(model) => {
+                model.acceleration(speed, pitch_radians, self.reverse) * self.physics.mass()
+            }
+            None => self
+                .physics
+                .driving_impedance_force(speed, pitch_radians, self.reverse),
+        }
+    }
+
+    /// Replaces the built-in resistive model used to find the
+    /// throttle and brake borders.
+    ///
+    /// The `model` receives `(speed, pitch_radians, reverse)` and
+    /// returns the resistive acceleration in m/s².
+    pub fn set_resistive_model<F>(&mut self, model: F)
+    where
+        F: Fn(f64, f64, bool) -> f64 + Send + Sync + 'static,
+    {
+        self.resistive_model = Some(ResistiveModel::new(model));
+    }
+
+    /// Restores the built-in resistive model of [VehiclePhysics].
+    pub fn clear_resistive_model(&mut self) {
+        self.resistive_model = None;
+    }
+
+    /// Puts the controller into neutral or takes it out.
+    ///
+    /// While in neutral, the controller lets the vehicle coast with
+    /// zero throttle, zero brake and no hand brake regardless of the
+    /// target, and the PIDs are frozen. Unlike an emergency stop, it
+    /// does not brake.
+    pub fn set_neutral(&mut self, neutral: bool) {
+        self.neutral = neutral;
+    }
+
+    pub fn is_neutral(&self) -> bool {
+        self.neutral
+    }
+
+    /// Estimates the seconds to reach `target_speed` from the last
+    /// measured speed, accelerating at `max_accel` or decelerating at
+    /// `max_decel` of the speed controller.
+    ///
+    /// It is a simple kinematic estimate that ignores the slope and
+    /// the resistive forces.
+    pub fn time_to_speed(&self, target_speed: f64) -> f64 {
+        let speed_delta = target_speed - self.measurement.speed;
+        if speed_delta >= 0.0 {
+            speed_delta / self.speed_controller.max_accel()
+        } else {
+            -speed_delta / self.speed_controller.max_decel()
+        }
+    }
+
+    /// Returns true if the last target steering angle exceeded the
+    /// steering limits and was clamped.
+    pub fn steering_saturated(&self) -> bool {
+        self.steer_controller.saturated
+    }
+
+    /// Gets the remaining difference in radians between the target
+    /// steering angle and the angle let through by `steering_ramp_rate`,
+    /// in the `steer_convention`. It is zero without a steering ramp.
+    pub fn steering_tracking_error(&self) -> f64 {
+        let Some(ramp) = &self.steering_ramp else {
+            return 0.0;
+        };
+        self.requested_steering_angle - ramp.value()
+    }
+
+    /// Gets the road grade in radians estimated by the
+    /// [GradeEstimator] if `grade_estimator_time_constant` is set.
+    pub fn estimated_grade_radians(&self) -> Option<f64> {
+        self.grade_estimator
+            .as_ref()
+            .and_then(|estimator| estimator.grade_radians())
+    }
+
+    /// Sets the current lateral accel (m/s²), e.g. measured or
+    /// computed from the yaw rate as `speed * yaw_rate`.
+    ///
+    /// It is kept until the next call, so update it on every step.
+    pub fn set_lateral_accel(&mut self, lateral_accel: f64) {
+        self.lateral_accel = lateral_accel;
+    }
+
+    /// Sets the yaw error in radians driving the
+    /// [Output::brake_bias], positive when the vehicle should yaw
+    /// further to the right.
+    pub fn set_yaw_correction(&mut self, yaw_error: f64) {
+        self.yaw_error = yaw_error;
+    }
+
+    /// Gets the longitudinal accel (m/s²) left by the last lateral
+    /// accel if `friction_coefficient` is set.
+    pub fn longitudinal_grip_budget(&self) -> Option<f64> {
+        self.friction_coefficient.map(|friction_coefficient| {
+            longitudinal_grip_budget(self.lateral_accel, friction_coefficient)
+        })
+    }
+
+    /// Gets the drag area in m² estimated by the [DragAreaEstimator]
+    /// if `drag_estimator_min_speed` is set.
+    pub fn estimated_drag_area(&self) -> Option<f64> {
+        self.drag_estimator
+            .as_ref()
+            .and_then(|estimator| estimator.drag_area())
+    }
+
+    /// Gets the [VerboseReport] of the last step if `verbose_report`
+    /// is enabled in [VehicleControllerInit].
+    pub fn verbose_report(&self) -> Option<&VerboseReport> {
+        self.verbose_report.as_ref()
+    }
+
+    /// Registers a callback invoked within every step with the raw
+    /// output of each PID that ran, e.g. for live plotting.
+    pub fn on_pid_output<F>(&mut self, hook: F)
+    where
+        F: FnMut(PidChannel, ControlOutput<f64>) + Send + 'static,
+    {
+        self.pid_hook = HookSlot(Some(PidHook::new(hook)));
+    }
+
+    pub fn clear_pid_output_hook(&mut self) {
+        self.pid_hook = HookSlot(None);
+    }
+
+    /// Gets the peak values since construction or the last
+    /// [reset_peaks](VehicleController::reset_peaks).
+    pub fn peaks(&self) -> Peaks {
+        self.peaks
+    }
+
+    pub fn reset_peaks(&mut self) {
+        self.peaks = Peaks::default();
+    }
+
+    /// Gets the accumulated control effort if `track_control_effort`
+    /// is enabled in [VehicleControllerInit].
+    pub fn control_effort(&self) -> Option<&ControlEffort> {
+        self.control_effort.as_ref()
+    }
+
+    /// Clears the accumulated control effort.
+    pub fn reset_control_effort(&mut self) {
+        if let Some(effort) = &mut self.control_effort {
+            *effort = ControlEffort::default();
         }
-        .build()
+    }
+
+    /// Gets the integral term of the speed PID for diagnostics. It
+    /// holds its value while the PID does not run.
+    pub fn speed_pid_integral(&self) -> f64 {
+        self.speed_controller.integral()
+    }
+
+    /// Gets the integral term of the acceleration PID for diagnostics,
+    /// like [speed_pid_integral](VehicleController::speed_pid_integral).
+    pub fn accel_pid_integral(&self) -> f64 {
+        self.accel_controller.integral()
+    }
+
+    /// Multiplies all gains of the speed PID by `factor` for coarse
+    /// tuning.
+    pub fn scale_speed_gains(&mut self, factor: f64) {
+        self.speed_controller.scale_gains(factor);
+    }
+
+    /// Multiplies all gains of the acceleration PID by `factor` for
+    /// coarse tuning.
+    pub fn scale_accel_gains(&mut self, factor: f64) {
+        self.accel_controller.scale_gains(factor);
+    }
+
+    /// Weights the speed tracking against [TargetRequest::accel] in
+    /// the setpoint accel, see [SpeedController::set_speed_priority].
+    pub fn set_speed_priority(&mut self, speed_priority: f64) {
+        self.speed_controller.set_speed_priority(speed_priority);
+    }
+
+    /// Selects whether [TargetRequest::accel] is a limit or a
+    /// setpoint. See [AccelMode].
+    pub fn set_accel_mode(&mut self, accel_mode: AccelMode) {
+        self.speed_controller.set_accel_mode(accel_mode);
     }
 
     /// Set target values for the controller.
+    ///
+    /// If ramp rates are configured in [VehicleControllerInit], the
+    /// respective targets move toward the requested values on the
+    /// following steps instead.
+    ///
+    /// A target speed below [FULL_STOP_SPEED_MS](crate::FULL_STOP_SPEED_MS)
+    /// ignores the target accel and decelerates by the `stopping_decel`
+    /// of the speed controller, see
+    /// [target_accel_overridden](VehicleController::target_accel_overridden).
+    ///
+    /// Non-finite fields are ignored and keep the previous target.
     pub fn set_target(&mut self, target: TargetRequest) {
         let TargetRequest {
             steering_angle,
             speed,
             accel,
         } = target;
-        self.steer_controller.set_target(steering_angle);
+        let finite_or = |value: f64, prev: f64| if value.is_finite() { value } else { prev };
+        let speed = finite_or(speed, self.requested_speed);
+        let accel = finite_or(accel, self.requested_accel);
+        let steering_angle = finite_or(steering_angle, self.requested_steering_angle);
+        self.requested_speed = speed;
+        self.requested_accel = quantize(accel, self.target_accel_step);
+        self.requested_steering_angle = steering_angle;
+        self.stop_decel = None;
+        self.decel_tracking = false;
+        self.emergency_stopping = false;
+
+        if self.steering_ramp.is_none() {
+            let steering_angle = self.steer_convention.to_right_positive(steering_angle);
+            self.steer_controller.set_target(steering_angle);
+        }
+        if self.speed_ramp.is_none() && self.accel_ramp.is_none() {
+            self.apply_speed_target(self.requested_speed, self.requested_accel);
+        }
+    }
+
+    /// Returns true if the target accel was replaced by the stopping
+    /// deceleration because the target speed is below the full stop
+    /// speed. See [SpeedController::set_target].
+    pub fn target_accel_overridden(&self) -> bool {
+        self.speed_controller.accel_overridden()
+    }
+
+    /// Follows a speed profile of `(time_sec, speed)` points, linearly
+    /// interpolated on the controller clock, which starts at zero on
+    /// construction and advances by `time_delta_sec` on every step.
+    ///
+    /// While the profile is set, it overrides the target speed of
+    /// [set_target](VehicleController::set_target) on every step,
+    /// while the target accel and steering angle are kept. Before the
+    /// first and after the last point, the speed of the nearest point
+    /// is held.
+    pub fn set_speed_profile(&mut self, mut profile: Vec<(f64, f64)>) {
+        profile.sort_by(|(lhs, _), (rhs, _)| lhs.total_cmp(rhs));
+        self.speed_profile = (!profile.is_empty()).then_some(profile);
+    }
+
+    pub fn clear_speed_profile(&mut self) {
+        self.speed_profile = None;
+    }
+
+    fn apply_speed_target(&mut self, speed: f64, accel: f64) {
+        if let Some(decel) = self.stop_decel {
+            if self.decel_tracking {
+                self.speed_controller.set_decel_target(decel);
+            } else {
+                self.speed_controller.set_stop_target(decel);
+            }
+            return;
+        }
+        let speed = quantize(speed, self.target_speed_step);
+        let speed = if self.allow_reverse {
+            speed
+        } else {
+            speed.max(0.0)
+        };
         self.speed_controller.set_target(speed, accel);
     }
 
+    /// Advances the speed profile and the target ramps. The speed ramp
+    /// starts from `current_speed` on the first step.
+    fn update_targets(&mut self, time_delta_sec: f64, current_speed: f64) {
+        if self.measurement.time_sec == 0.0 {
+            if let Some(ramp) = &mut self.speed_ramp {
+                let speed = current_speed.abs();
+                ramp.reset_to(if self.reverse { -speed } else { speed });
+            }
+        }
+
+        let time_sec = self.measurement.time_sec + time_delta_sec;
+        let profile_speed = self
+            .speed_profile
+            .as_deref()
+            .map(|profile| interpolate_profile(profile, time_sec));
+        if let Some(speed) = profile_speed {
+            self.requested_speed = speed;
+        }
+
+        if let Some(ramp) = &mut self.steering_ramp {
+            let steering_angle = ramp.update(self.requested_steering_angle, time_delta_sec);
+            let steering_angle = self.steer_convention.to_right_positive(steering_angle);
+            self.steer_controller.set_target(steering_angle);
+        }
+
+        let is_ramping = self.speed_ramp.is_some() || self.accel_ramp.is_some();
+        if profile_speed.is_some() || is_ramping {
+            let speed = match &mut self.speed_ramp {
+                Some(ramp) => ramp.update(self.requested_speed, time_delta_sec),
+                None => self.requested_speed,
+            };
+            let accel = match &mut self.accel_ramp {
+                Some(ramp) => ramp.update(self.requested_accel, time_delta_sec),
+                None => self.requested_accel,
+            };
+            self.apply_speed_target(speed, accel);
+        }
+    }
+
+    /// Brakes to a stop with a deceleration bounded by `comfort_decel`
+    /// (m/s²) and holds the vehicle in [Status::FullStop] once it
+    /// stands still. The steering target is kept.
+    ///
+    /// It lasts until the next [set_target](VehicleController::set_target).
+    pub fn stop_and_hold(&mut self, comfort_decel: f64) {
+        self.stop_decel = Some(comfort_decel);
+        self.decel_tracking = false;
+        self.speed_controller.set_stop_target(comfort_decel);
+    }
+
+    /// Brakes at the deceleration `decel` (m/s²) regardless of the
+    /// target speed and the [AccelMode], tracked by the accel loop,
+    /// and holds the vehicle in [Status::FullStop] once it stands
+    /// still. The steering target is kept.
+    ///
+    /// It lasts until the next [set_target](VehicleController::set_target).
+    pub fn brake_at(&mut self, decel: f64) {
+        self.stop_decel = Some(decel);
+        self.decel_tracking = true;
+        self.speed_controller.set_decel_target(decel);
+    }
+
+    /// Brakes with the full brake until the vehicle stands still and
+    /// holds it in [Status::FullStop], bypassing `max_normal_brake`,
+    /// the pedal curve and the pedal jerk limit. The steering target
+    /// is kept.
+    ///
+    /// It lasts until the next [set_target](VehicleController::set_target).
+    pub fn emergency_stop(&mut self) {
+        self.emergency_stopping = true;
+        self.stop_and_hold(self.physics.max_deceleration());
+    }
+
+    pub fn is_emergency_stopping(&self) -> bool {
+        self.emergency_stopping
+    }
+
     /// Produces a controlling command.
     ///
     /// # Parameters
     /// - `time_delta_sec` is elapsed seconds since last step.
-    /// - `current_speed` is the current speed of the car.
+    /// - `current_speed` is the current speed of the car. It is taken
+    ///   as a magnitude, and the travel direction follows the reverse
+    ///   gear of [Output::reverse].
     /// - `pitch_radians` is the current pitch angle of the car.
-    pub fn step(
+    ///
+    /// # Reverse
+    /// While reversing, the pedals act in the direction of travel like
+    /// driving forward: throttle in reverse gear speeds the vehicle up
+    /// backward and brake slows it down, and the throttle and brake
+    /// borders account for the slope in the backward direction.
+    ///
+    /// # Panics
+    /// In debug builds, it panics if the output fails [Output::validate].
+    pub fn step(
+        &mut self,
+        time_delta_sec: f64,
+        current_speed: f64,
+        pitch_radians: f64,
+    ) -> (Output, Report) {
+        let (output, report) = self.step_unchecked(time_delta_sec, current_speed, pitch_radians);
+        debug_assert!(
+            output.validate_with_overlap(self.max_pedal_overlap).is_ok(),
+            "invalid controller output {output:?}"
+        );
+        self.last_step = Some((output.clone(), report.clone()));
+        (output, report)
+    }
+
+    /// Produces a controlling command like [step](VehicleController::step).
+    ///
+    /// If `validate_output` is enabled in [VehicleControllerInit], it
+    /// returns an error when the output fails [Output::validate]
+    /// instead of emitting it.
+    pub fn try_step(
+        &mut self,
+        time_delta_sec: f64,
+        current_speed: f64,
+        pitch_radians: f64,
+    ) -> Result<(Output, Report), OutputError> {
+        let (output, report) = self.step_unchecked(time_delta_sec, current_speed, pitch_radians);
+        if self.validate_output {
+            output.validate_with_overlap(self.max_pedal_overlap)?;
+        }
+        self.last_step = Some((output.clone(), report.clone()));
+        Ok((output, report))
+    }
+
+    /// Gets the output returned by the last step, if any.
+    ///
+    /// Failed [try_step](VehicleController::try_step) calls and
+    /// [step_accel](VehicleController::step_accel) do not update it.
+    pub fn last_output(&self) -> Option<&Output> {
+        self.last_step.as_ref().map(|(output, _)| output)
+    }
+
+    /// Gets the report returned by the last step, if any, like
+    /// [last_output](VehicleController::last_output).
+    pub fn last_report(&self) -> Option<&Report> {
+        self.last_step.as_ref().map(|(_, report)| report)
+    }
+
+    /// Produces a controlling command like [step](VehicleController::step)
+    /// from the velocity in the vehicle body frame.
+    ///
+    /// `velocity_body` is `[longitudinal, lateral, vertical]` in m/s.
+    /// Only the longitudinal component is used as the control speed,
+    /// so lateral sliding does not count as forward speed.
+    pub fn step_with_velocity(
+        &mut self,
+        time_delta_sec: f64,
+        velocity_body: [f64; 3],
+        pitch_radians: f64,
+    ) -> (Output, Report) {
+        let [longitudinal_speed, _, _] = velocity_body;
+        self.step(time_delta_sec, longitudinal_speed, pitch_radians)
+    }
+
+    /// Produces a controlling command like [step](VehicleController::step)
+    /// with the longitudinal accel (m/s²) measured along the vehicle
+    /// x-axis, e.g. by an IMU, instead of differencing the speed.
+    ///
+    /// The accel is used as is, without the speed filter and the
+    /// `accel_estimate_limit`. Gravity must be already removed from it
+    /// unless `measured_accel_includes_gravity` is set.
+    pub fn step_with_measured_accel(
+        &mut self,
+        time_delta_sec: f64,
+        current_speed: f64,
+        measured_accel: f64,
+        pitch_radians: f64,
+    ) -> (Output, Report) {
+        let measured_accel = if self.measured_accel_includes_gravity {
+            gravity_compensated_accel(measured_accel, pitch_radians)
+        } else {
+            measured_accel
+        };
+        // The controller tracks the rate of the speed magnitude, which
+        // is opposite to the body accel while reversing.
+        let accel = if self.reverse {
+            -measured_accel
+        } else {
+            measured_accel
+        };
+        self.measurement.accel_override = Some(accel);
+        let step = self.step(time_delta_sec, current_speed, pitch_radians);
+        self.measurement.accel_override = None;
+        step
+    }
+
+    /// Produces a controlling command like [step](VehicleController::step)
+    /// from the readings of `sensors`.
+    ///
+    /// The measured accel is used like
+    /// [step_with_measured_accel](VehicleController::step_with_measured_accel)
+    /// if the sensors provide it.
+    pub fn step_from_sensors(
+        &mut self,
+        sensors: &impl VehicleSensors,
+        time_delta_sec: f64,
+    ) -> (Output, Report) {
+        let speed = sensors.speed();
+        let pitch_radians = sensors.pitch();
+        match sensors.accel() {
+            Some(accel) => {
+                self.step_with_measured_accel(time_delta_sec, speed, accel, pitch_radians)
+            }
+            None => self.step(time_delta_sec, speed, pitch_radians),
+        }
+    }
+
+    /// Steps the controller on the state of `vehicle` and applies the
+    /// output to it.
+    ///
+    /// The time delta is the elapsed simulation time between
+    /// `prev_snapshot` and `snapshot`. The controller is not stepped
+    /// and `None` is returned if the snapshots have different ids, i.e.
+    /// the world was reloaded in between, or if the time delta is not
+    /// positive, e.g. for the same snapshot twice.
+    ///
+    /// ```no_run
+    /// use carla::client::{Client, Vehicle};
+    /// use carla_ackermann::{TargetRequest, VehicleController};
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let client = Client::connect("127.0.0.1", 2000, None);
+    /// let mut world = client.world();
+    /// let spawn_point = world.map().recommended_spawn_points().get(0).unwrap();
+    /// let mut vehicle: Vehicle = world
+    ///     .actor_builder("vehicle.tesla.model3")?
+    ///     .spawn_vehicle(&spawn_point)?;
+    ///
+    /// let mut controller = VehicleController::from_physics_control(&vehicle.physics_control(), None);
+    /// controller.set_target(TargetRequest {
+    ///     steering_angle: 0.0,
+    ///     speed: 5.0,
+    ///     accel: 1.0,
+    /// });
+    ///
+    /// let mut prev_snapshot = world.wait_for_tick();
+    /// loop {
+    ///     let snapshot = world.wait_for_tick();
+    ///     match controller.tick(&mut vehicle, &snapshot, &prev_snapshot) {
+    ///         Some(report) => println!("{report}"),
+    ///         None => println!("skipped snapshot {}", snapshot.id()),
+    ///     }
+    ///     prev_snapshot = snapshot;
+    /// }
+    /// # }
+    /// ```
+    pub fn tick(
+        &mut self,
+        vehicle: &mut Vehicle,
+        snapshot: &WorldSnapshot,
+        prev_snapshot: &WorldSnapshot,
+    ) -> Option<Report> {
+        if snapshot.id() != prev_snapshot.id() {
+            return None;
+        }
+        let time_delta_sec =
+            snapshot.timestamp().elapsed_seconds - prev_snapshot.timestamp().elapsed_seconds;
+        if time_delta_sec <= 0.0 {
+            return None;
+        }
+
+        let (output, report) = self.step(
+            time_delta_sec,
+            vehicle_speed(vehicle),
+            vehicle_pitch(vehicle),
+        );
+        vehicle.apply_control(&VehicleControl {
+            throttle: output.throttle as f32,
+            steer: output.steer as f32,
+            brake: output.brake as f32,
+            hand_brake: output.hand_brake,
+            reverse: output.reverse,
+            manual_gear_shift: false,
+            gear: 0,
+        });
+        Some(report)
+    }
+
+    /// Produces a controlling command like [step](VehicleController::step)
+    /// and reports the longitudinal slip ratio computed from the wheel
+    /// angular speeds in rad/s.
+    ///
+    /// The slip ratio is `(omega * radius - speed) / speed` with the
+    /// mean wheel angular speed, where the speed is bounded below by
+    /// [MIN_SLIP_REFERENCE_SPEED_MS] to avoid blowing up at standstill.
+    /// If `max_slip_ratio` is configured, the throttle is scaled down
+    /// by `max_slip_ratio / slip_ratio` when it is exceeded.
+    pub fn step_with_wheel_speeds(
+        &mut self,
+        time_delta_sec: f64,
+        current_speed: f64,
+        pitch_radians: f64,
+        wheel_omegas: &[f64],
+    ) -> (Output, Report) {
+        let slip_ratio = (!wheel_omegas.is_empty()).then(|| {
+            let mean_omega = wheel_omegas.iter().sum::<f64>() / wheel_omegas.len() as f64;
+            let wheel_speed = mean_omega.abs() * self.physics.wheel_radius();
+            let speed = current_speed.abs();
+            (wheel_speed - speed) / speed.max(MIN_SLIP_REFERENCE_SPEED_MS)
+        });
+
+        let (mut output, mut report) = self.step(time_delta_sec, current_speed, pitch_radians);
+        report.slip_ratio = slip_ratio;
+
+        if let (Some(slip_ratio), Some(max_slip_ratio)) = (slip_ratio, self.max_slip_ratio) {
+            if slip_ratio > max_slip_ratio && max_slip_ratio > 0.0 {
+                output.throttle *= max_slip_ratio / slip_ratio;
+                if self.round_output_to_f32 {
+                    output = output.round_to_f32();
+                }
+            }
+        }
+
+        self.last_step = Some((output.clone(), report.clone()));
+        (output, report)
+    }
+
+    /// Runs the speed controller and returns the commanded
+    /// longitudinal acceleration in m/s² without mapping it to pedals.
+    ///
+    /// It is intended for external plants that take an acceleration
+    /// command directly, so the throttle and brake borders of
+    /// [VehiclePhysics] are skipped. Steering is not computed.
+    ///
+    /// The gear, including the reverse hysteresis and interval, and the
+    /// startup blanking are updated as in [step](VehicleController::step),
+    /// so both can be mixed on one controller.
+    ///
+    /// The commanded acceleration is the net acceleration and does not
+    /// depend on the road slope. It is bounded by the same grip budget
+    /// and comfort limits as the setpoint of
+    /// [step](VehicleController::step). `pitch_radians` is accepted for
+    /// symmetry with [step](VehicleController::step).
+    pub fn step_accel(
+        &mut self,
+        time_delta_sec: f64,
+        current_speed: f64,
+        _pitch_radians: f64,
+    ) -> f64 {
+        assert!(time_delta_sec > 0.0);
+        self.update_targets(time_delta_sec, current_speed);
+        self.first_step = false;
+
+        let SpeedControl {
+            setpoint_accel,
+            full_stop,
+            ..
+        } = self.step_speed(time_delta_sec, current_speed);
+        let setpoint_accel = limit_setpoint_accel(
+            setpoint_accel,
+            full_stop,
+            self.longitudinal_grip_budget(),
+            (self.comfort_accel_limit, self.comfort_decel_limit),
+        );
+        self.accel_controller.set_target_accel(setpoint_accel);
+        setpoint_accel
+    }
+
+    fn step_unchecked(
         &mut self,
         time_delta_sec: f64,
         current_speed: f64,
@@ -186,28 +1866,362 @@ impl VehicleController {
     ) -> (Output, Report) {
         assert!(time_delta_sec > 0.0);
 
+        let Some(control_period_sec) = self.control_period_sec else {
+            return self.compute_substeps(time_delta_sec, current_speed, pitch_radians);
+        };
+
+        // Hold the last output until a full control period elapsed.
+        // The tolerance absorbs rounding in the accumulated time.
+        self.pending_time_sec += time_delta_sec;
+        if let Some(held_step) = &self.held_step {
+            if self.pending_time_sec < control_period_sec - 1e-9 {
+                return held_step.clone();
+            }
+        }
+
+        let elapsed_sec = std::mem::take(&mut self.pending_time_sec);
+        let step = self.compute_substeps(elapsed_sec, current_speed, pitch_radians);
+        self.held_step = Some(step.clone());
+        step
+    }
+
+    /// Runs [compute_step](VehicleController::compute_step) in
+    /// sub-steps no longer than `max_substep_sec`.
+    fn compute_substeps(
+        &mut self,
+        time_delta_sec: f64,
+        current_speed: f64,
+        pitch_radians: f64,
+    ) -> (Output, Report) {
+        let num_substeps = match self.max_substep_sec {
+            Some(max_substep_sec) if max_substep_sec > 0.0 => {
+                (time_delta_sec / max_substep_sec - 1e-9).ceil().max(1.0) as usize
+            }
+            _ => 1,
+        };
+        if num_substeps == 1 {
+            return self.compute_step(time_delta_sec, current_speed, pitch_radians);
+        }
+
+        let substep_sec = time_delta_sec / num_substeps as f64;
+        let current_speed = current_speed.abs();
+        let Measurement {
+            last_input_speed,
+            repeat_count,
+            accel_override,
+            ..
+        } = self.measurement;
+        let prev_speed = last_input_speed.unwrap_or(current_speed);
+        let is_first_step = self.first_step;
+
+        for index in 1..num_substeps {
+            let ratio = index as f64 / num_substeps as f64;
+            let speed = prev_speed + ratio * (current_speed - prev_speed);
+            self.measurement.accel_override = accel_override;
+            self.compute_step(substep_sec, speed, pitch_radians);
+        }
+
+        // Count the repeated inputs per sample rather than per sub-step
+        self.measurement.accel_override = accel_override;
+        self.measurement.last_input_speed = last_input_speed;
+        self.measurement.repeat_count = repeat_count;
+        let (output, mut report) = self.compute_step(substep_sec, current_speed, pitch_radians);
+        report.first_step = is_first_step;
+        (output, report)
+    }
+
+    /// Updates the measurement and runs the speed controller, which is
+    /// frozen in neutral, then changes the gear. The vehicle is stopped
+    /// and held until a pending gear change.
+    fn step_speed(&mut self, time_delta_sec: f64, current_speed: f64) -> SpeedControl {
         let Self {
             measurement,
-            physics,
             speed_controller,
             accel_controller,
-            steer_controller,
+            neutral,
+            reverse,
+            thresholds,
+            blanking_steps,
+            allow_reverse,
+            reverse_hysteresis,
+            reverse_dwell_sec,
+            gear_dwell_time_sec,
+            gear_change_decel,
+            min_reverse_interval_sec,
+            last_gear_change_time_sec,
+            ..
         } = self;
 
         // Save measurements
-        measurement.update(time_delta_sec, current_speed);
+        let current_speed = measurement.update(
+            time_delta_sec,
+            current_speed.abs(),
+            thresholds.full_stop_speed,
+        );
+        // The speed is a magnitude, so take the travel direction from
+        // the gear of the last step.
+        let signed_speed = if *reverse {
+            -current_speed
+        } else {
+            current_speed
+        };
 
-        // Compute steer ratio
-        let steer = steer_controller.steer_ratio();
+        // Suppress the acceleration estimate during startup blanking
+        if *blanking_steps > 0 {
+            *blanking_steps -= 1;
+            measurement.accel = 0.0;
+        }
+
+        // Run speed controller. It is frozen in neutral.
+        let SpeedControl {
+            setpoint_accel,
+            delta_accel,
+            full_stop,
+            pid_output,
+        } = if *neutral {
+            SpeedControl {
+                setpoint_accel: accel_controller.target_accel(),
+                delta_accel: 0.0,
+                full_stop: false,
+                pid_output: None,
+            }
+        } else {
+            speed_controller.step(signed_speed)
+        };
+
+        // Switch into reverse below -reverse_hysteresis and back to
+        // forward at or above reverse_hysteresis, unless reverse is
+        // disallowed.
+        let is_standing = measurement.speed < thresholds.stand_still_speed;
+        let is_gear_pending = {
+            let target_speed = speed_controller.target_speed();
+            let next = if !*allow_reverse {
+                false
+            } else if *reverse {
+                target_speed < *reverse_hysteresis
+            } else {
+                target_speed < -*reverse_hysteresis
+            };
+
+            // Change the gear only at standstill, optionally after a
+            // dwell, so that the travel direction follows the gear. The
+            // tolerance absorbs rounding in the accumulated time.
+            let is_switching = match *reverse_dwell_sec {
+                _ if next == *reverse => true,
+                Some(dwell_sec) => {
+                    *gear_dwell_time_sec = if is_standing {
+                        *gear_dwell_time_sec + time_delta_sec
+                    } else {
+                        0.0
+                    };
+                    *gear_dwell_time_sec >= dwell_sec - 1e-9
+                }
+                None => is_standing,
+            };
+            // Defer the gear change until the last one is old enough
+            let is_locked = next != *reverse
+                && match (*min_reverse_interval_sec, *last_gear_change_time_sec) {
+                    (Some(interval_sec), Some(last_sec)) => {
+                        measurement.time_sec - last_sec < interval_sec - 1e-9
+                    }
+                    _ => false,
+                };
+            if is_switching && !is_locked {
+                if next != *reverse {
+                    *last_gear_change_time_sec = Some(measurement.time_sec);
+                }
+                *gear_dwell_time_sec = 0.0;
+                *reverse = next;
+            }
+            next != *reverse
+        };
+
+        // Stop and hold the vehicle until the pending gear change
+        let full_stop = full_stop || (is_gear_pending && is_standing);
+        let setpoint_accel = if is_gear_pending && !is_standing {
+            setpoint_accel.min(-gear_change_decel.abs())
+        } else {
+            setpoint_accel
+        };
 
-        // Run speed controller
+        SpeedControl {
+            setpoint_accel,
+            delta_accel,
+            full_stop,
+            pid_output,
+        }
+    }
+
+    fn compute_step(
+        &mut self,
+        time_delta_sec: f64,
+        current_speed: f64,
+        pitch_radians: f64,
+    ) -> (Output, Report) {
+        self.update_targets(time_delta_sec, current_speed);
+        let steering_tracking_error = self.steering_tracking_error();
+        let is_first_step = std::mem::take(&mut self.first_step);
+        let is_blanking = self.blanking_steps > 0;
         let SpeedControl {
             setpoint_accel,
             delta_accel,
             full_stop,
-        } = speed_controller.step(current_speed);
+            pid_output: speed_pid_output,
+        } = self.step_speed(time_delta_sec, current_speed);
+        let reverse = self.reverse;
+
+        let Self {
+            measurement,
+            physics,
+            speed_controller,
+            accel_controller,
+            steer_controller,
+            steer_at_standstill,
+            standstill_integral_gain,
+            instability,
+            descent_hand_brake_threshold,
+            resistive_model,
+            pedal_blend_width,
+            neutral,
+            pitch_filter,
+            throttle_border_offset,
+            brake_border_offset,
+            low_speed_max_decel,
+            low_speed_decel_speed,
+            control_effort,
+            pedal_jerk_limiter,
+            max_pedal_overlap,
+            pid_hook,
+            steer_ratio_limit,
+            grade_estimator,
+            efficiency_weight,
+            verbose_report,
+            friction_coefficient,
+            lateral_accel,
+            steady_hold_speed_error,
+            steady_hold_accel_error,
+            steady_hold_dwell_sec,
+            steady_hold_release_speed_error,
+            steady_hold_time_sec,
+            steady_holding,
+            steady_hold_pedals,
+            hand_brake_dwell_sec,
+            full_stop_time_sec,
+            round_output_to_f32,
+            comfort_accel_limit,
+            comfort_decel_limit,
+            drag_estimator,
+            yaw_correction_gain,
+            max_brake_bias,
+            yaw_error,
+            pedal_curve,
+            creep_accel,
+            peaks,
+            max_normal_brake,
+            emergency_stopping,
+            thresholds,
+            ..
+        } = self;
+
+        // Estimate the grade from the response to the last command
+        let estimated_grade_radians = grade_estimator
+            .as_mut()
+            .and_then(|estimator| estimator.update(measurement.accel, time_delta_sec));
+
+        // Compute steer ratio
+        let steer_ratio_limit = steer_ratio_limit.clamp(0.0, 1.0);
+        let steer = steer_controller
+            .steer_ratio()
+            .clamp(-steer_ratio_limit, steer_ratio_limit);
+
+        // Smooth the pitch input
+        let pitch_radians = match pitch_filter {
+            Some(filter) => filter.update(pitch_radians, time_delta_sec),
+            None => pitch_radians,
+        };
+
+        // Fit the drag area to the coasting response of the last step
+        if let Some(estimator) = drag_estimator {
+            let drag_area = estimator.update(
+                physics,
+                measurement.speed,
+                measurement.accel,
+                pitch_radians,
+                reverse,
+            );
+            if let Some(drag_area) = drag_area {
+                physics.set_drag_area(drag_area);
+            }
+        }
+
+        let (throttle_lower_border, brake_upper_border) = pedal_borders(
+            physics,
+            resistive_model.as_ref(),
+            (*throttle_border_offset, *brake_border_offset),
+            measurement.speed,
+            pitch_radians,
+            reverse,
+        );
 
         // Run acceleration controller
+        if let Some(ki) = *standstill_integral_gain {
+            let is_standing = !full_stop && measurement.speed < thresholds.stand_still_speed;
+            accel_controller.set_integral_override(is_standing.then_some(ki));
+        }
+
+        // Bias toward coasting for efficiency. The coasting accel is
+        // the accel at the throttle border, where the vehicle rolls
+        // without pedals.
+        let efficiency_weight = efficiency_weight.clamp(0.0, 1.0);
+        let coast_accel = throttle_lower_border - *throttle_border_offset;
+        let is_efficiency_coasting = !full_stop
+            && (setpoint_accel - coast_accel).abs() < efficiency_weight * EFFICIENCY_COAST_BAND_MS2;
+        let setpoint_accel = if !full_stop && setpoint_accel > coast_accel {
+            let scale = 1.0 - efficiency_weight * (1.0 - EFFICIENCY_ACCEL_SCALE);
+            coast_accel + (setpoint_accel - coast_accel) * scale
+        } else {
+            setpoint_accel
+        };
+
+        let grip_budget = friction_coefficient.map(|friction_coefficient| {
+            longitudinal_grip_budget(*lateral_accel, friction_coefficient)
+        });
+        let setpoint_accel = limit_setpoint_accel(
+            setpoint_accel,
+            full_stop,
+            grip_budget,
+            (*comfort_accel_limit, *comfort_decel_limit),
+        );
+
+        // Freeze the pedal at steady state until the speed error grows
+        let is_steady_holding = match *steady_hold_speed_error {
+            Some(max_speed_error) if !full_stop && !*neutral => {
+                let speed_error = (speed_controller.target_speed().abs() - measurement.speed).abs();
+                if *steady_holding {
+                    *steady_holding = speed_error <= *steady_hold_release_speed_error;
+                    if !*steady_holding {
+                        *steady_hold_time_sec = 0.0;
+                    }
+                } else {
+                    let accel_error = (setpoint_accel - measurement.accel).abs();
+                    let is_steady =
+                        speed_error < max_speed_error && accel_error < *steady_hold_accel_error;
+                    *steady_hold_time_sec = if is_steady {
+                        *steady_hold_time_sec + time_delta_sec
+                    } else {
+                        0.0
+                    };
+                    *steady_holding = *steady_hold_time_sec >= *steady_hold_dwell_sec;
+                }
+                *steady_holding
+            }
+            _ => {
+                *steady_hold_time_sec = 0.0;
+                *steady_holding = false;
+                false
+            }
+        };
+
         accel_controller.set_target_accel(setpoint_accel);
         if full_stop {
             accel_controller.reset_target_pedal();
@@ -215,55 +2229,244 @@ impl VehicleController {
         let AccelControl {
             target_pedal,
             pedal_delta,
-        } = accel_controller.step(measurement.accel);
-
-        let reverse = speed_controller.target_speed() < 0.0;
-        let throttle_lower_border =
-            physics.driving_impedance_acceleration(measurement.speed, pitch_radians, reverse);
-        let brake_upper_border = throttle_lower_border + physics.lay_off_engine_acceleration();
-
-        let (status_kind, output) = if full_stop {
-            let kind = Status::FullStop;
-            let output = Output {
-                hand_brake: true,
-                steer,
-                reverse,
-                brake: 1.0,
-                throttle: 0.0,
+            pid_output: accel_pid_output,
+        } = if is_blanking || *neutral || is_efficiency_coasting || is_steady_holding {
+            accel_controller.hold()
+        } else {
+            accel_controller.step(measurement.accel)
+        };
+
+        // Keep the PID terms before the hook consumes the outputs
+        let (speed_pid_terms, accel_pid_terms) = if verbose_report.is_some() {
+            (
+                speed_pid_output.as_ref().map(PidTerms::from),
+                accel_pid_output.as_ref().map(PidTerms::from),
+            )
+        } else {
+            (None, None)
+        };
+
+        // Report the raw PID outputs
+        if let Some(hook) = &mut pid_hook.0 {
+            if let Some(output) = speed_pid_output {
+                hook.call(PidChannel::Speed, output);
+            }
+            if let Some(output) = accel_pid_output {
+                hook.call(PidChannel::Accel, output);
+            }
+        }
+
+        let max_pedal = accel_controller.max_pedal();
+
+        // Widen the brake range at low speed where the brakes
+        // decelerate more than the physics max deceleration.
+        let max_brake_pedal = max_brake_pedal(
+            max_pedal,
+            physics.max_deceleration(),
+            (*low_speed_max_decel, *low_speed_decel_speed),
+            measurement.speed,
+        );
+
+        let is_emergency_braking = *emergency_stopping && !full_stop;
+        let (status_kind, throttle, brake) = if is_emergency_braking {
+            (Status::Braking, 0.0, 1.0)
+        } else if *neutral || is_efficiency_coasting {
+            (Status::Coasting, 0.0, 0.0)
+        } else if full_stop {
+            match *creep_accel {
+                Some(creep_accel) => {
+                    // Counter the creep, braking harder while rolling
+                    let decel = creep_accel + CREEP_HOLD_GAIN * measurement.speed;
+                    let brake_ratio = (decel / max_brake_pedal).clamp(0.0, 1.0);
+                    (
+                        Status::FullStop,
+                        0.0,
+                        accel_controller.brake_command(brake_ratio),
+                    )
+                }
+                None => (Status::FullStop, 0.0, 1.0),
+            }
+        } else if *max_pedal_overlap > 0.0 {
+            // Extend both channels across the coasting gap into each
+            // other's regime, then cap the smaller one.
+            let overlap = max_pedal_overlap.min(1.0);
+            let shift = (throttle_lower_border - brake_upper_border) / 2.0 + overlap * max_pedal;
+            let throttle =
+                ((target_pedal - throttle_lower_border + shift) / max_pedal).clamp(0.0, 1.0);
+            let brake_ratio =
+                ((brake_upper_border + shift - target_pedal) / max_brake_pedal).clamp(0.0, 1.0);
+            let brake = accel_controller.brake_command(brake_ratio);
+
+            if throttle >= brake {
+                (Status::Accelerating, throttle, brake.min(overlap))
+            } else {
+                (Status::Braking, throttle.min(overlap), brake)
+            }
+        } else if *pedal_blend_width > 0.0 && throttle_lower_border > brake_upper_border {
+            // Interpolate between the regimes near the borders. The
+            // width is limited by the coasting gap so that throttle
+            // and brake never overlap. Without a gap, e.g. due to the
+            // border offsets, the regimes switch hard.
+            let width = pedal_blend_width.min(throttle_lower_border - brake_upper_border);
+            let throttle_weight = blend_weight(target_pedal - throttle_lower_border, width);
+            let brake_weight = blend_weight(brake_upper_border - target_pedal, width);
+
+            let throttle_ratio =
+                ((target_pedal - throttle_lower_border) / max_pedal).clamp(0.0, 1.0);
+            let brake_ratio =
+                ((brake_upper_border - target_pedal) / max_brake_pedal).clamp(0.0, 1.0);
+            let brake_intercept = accel_controller.brake_intercept();
+
+            let throttle = throttle_weight * throttle_ratio;
+            let brake = brake_weight * (brake_intercept + (1.0 - brake_intercept) * brake_ratio);
+
+            let kind = if throttle > 0.0 {
+                Status::Accelerating
+            } else if brake > 0.0 {
+                Status::Braking
+            } else {
+                Status::Coasting
             };
-            (kind, output)
+            (kind, throttle, brake)
         } else if target_pedal > throttle_lower_border {
-            let kind = Status::Accelerating;
-            let throttle = (target_pedal - throttle_lower_border) / accel_controller.max_pedal();
-            let output = Output {
-                hand_brake: false,
-                steer,
-                reverse,
-                brake: 0.0,
-                throttle,
-            };
-            (kind, output)
+            let throttle = ((target_pedal - throttle_lower_border) / max_pedal).clamp(0.0, 1.0);
+            (Status::Accelerating, throttle, 0.0)
         } else if target_pedal > brake_upper_border {
-            let kind = Status::Coasting;
-            let output = Output {
-                hand_brake: false,
-                steer,
-                reverse,
-                brake: 0.0,
-                throttle: 0.0,
-            };
-            (kind, output)
+            (Status::Coasting, 0.0, 0.0)
         } else {
-            let kind = Status::Braking;
-            let brake = (brake_upper_border - target_pedal) / accel_controller.max_pedal();
-            let output = Output {
-                hand_brake: false,
-                steer,
-                reverse,
-                brake,
-                throttle: 0.0,
+            let brake_ratio =
+                ((brake_upper_border - target_pedal) / max_brake_pedal).clamp(0.0, 1.0);
+            let brake = accel_controller.brake_command(brake_ratio);
+            (Status::Braking, 0.0, brake)
+        };
+
+        let (throttle, brake) = (pedal_curve.apply(throttle), pedal_curve.apply(brake));
+
+        // Keep the pedals of the first steady step while holding
+        let (throttle, brake) = if is_steady_holding {
+            *steady_hold_pedals.get_or_insert((throttle, brake))
+        } else {
+            *steady_hold_pedals = None;
+            (throttle, brake)
+        };
+
+        // The grade estimator expects the target pedal to be achieved,
+        // except when standing or holding where the accel is zero.
+        if let Some(estimator) = grade_estimator {
+            let is_moving = !full_stop
+                && !*neutral
+                && !is_blanking
+                && measurement.speed.abs() >= thresholds.stand_still_speed;
+            let expected_accel = target_pedal.clamp(
+                brake_upper_border - max_brake_pedal,
+                throttle_lower_border + max_pedal,
+            );
+            estimator.set_expected_accel(is_moving.then_some(expected_accel));
+        }
+
+        if let Some(estimator) = drag_estimator {
+            let is_coasting = throttle == 0.0 && brake == 0.0 && !full_stop && !is_blanking;
+            estimator.set_coasting(is_coasting);
+        }
+
+        // Limit the pedal jerk, except for the hold at full stop, from
+        // which the pedal is released smoothly
+        let (throttle, brake) = match pedal_jerk_limiter {
+            Some(limiter) if status_kind == Status::FullStop => {
+                limiter.reset_to(throttle - brake);
+                (throttle, brake)
+            }
+            Some(limiter) => {
+                let pedal = limiter
+                    .update(throttle - brake, time_delta_sec)
+                    .clamp(-1.0, 1.0);
+                Output::split_unified_pedal(pedal)
+            }
+            None => (throttle, brake),
+        };
+
+        // Reserve the brake above the normal cap for emergencies
+        let (throttle, brake) = if is_emergency_braking {
+            (0.0, 1.0)
+        } else if status_kind == Status::FullStop {
+            (throttle, brake)
+        } else {
+            (throttle, brake.min(max_normal_brake.clamp(0.0, 1.0)))
+        };
+
+        // The full brake cannot reach the setpoint accel on this grade
+        let decel_authority_exceeded =
+            !full_stop && setpoint_accel < brake_upper_border - max_brake_pedal;
+
+        let descent_hand_brake = status_kind == Status::Braking
+            && descent_hand_brake_threshold
+                .map(|threshold| brake >= threshold && measurement.accel > 0.0)
+                .unwrap_or(false);
+        // Engage the hand brake after a dwell at full stop. The
+        // tolerance absorbs rounding in the accumulated time.
+        *full_stop_time_sec = if status_kind == Status::FullStop {
+            *full_stop_time_sec + time_delta_sec
+        } else {
+            0.0
+        };
+        let is_parked =
+            status_kind == Status::FullStop && *full_stop_time_sec >= *hand_brake_dwell_sec - 1e-9;
+        let hand_brake = is_parked || descent_hand_brake;
+        let steer = if status_kind == Status::FullStop && !*steer_at_standstill {
+            0.0
+        } else {
+            steer
+        };
+
+        // Bias the brake toward the side to yaw to
+        let brake_bias = yaw_correction_gain.map(|gain| {
+            let max_brake_bias = max_brake_bias.clamp(0.0, 1.0);
+            (gain * *yaw_error).clamp(-max_brake_bias, max_brake_bias)
+        });
+
+        let output = Output {
+            throttle,
+            brake,
+            steer,
+            reverse,
+            hand_brake,
+            brake_bias,
+        };
+        let output = if *round_output_to_f32 {
+            output.round_to_f32()
+        } else {
+            output
+        };
+
+        if let Some(effort) = control_effort {
+            effort.push(time_delta_sec, output.throttle, output.brake);
+        }
+
+        // The accel of the first step is measured from zero speed
+        let peak_accel = if is_first_step {
+            0.0
+        } else {
+            measurement.accel
+        };
+        peaks.push(peak_accel, measurement.speed, output.steer);
+
+        // Detect oscillation
+        let instability_score = instability.push(pedal_delta);
+
+        // Compute authority utilization
+        let authority_utilization = {
+            let speed_limit = speed_controller.output_limit();
+            let speed_utilization = if speed_limit > 0.0 {
+                delta_accel.abs() / speed_limit
+            } else {
+                0.0
             };
-            (kind, output)
+            let pedal_utilization = output.throttle.max(output.brake);
+            let steer_utilization = output.steer.abs();
+            speed_utilization
+                .max(pedal_utilization)
+                .max(steer_utilization)
+                .clamp(0.0, 1.0)
         };
 
         let report = Report {
@@ -272,8 +2475,1513 @@ impl VehicleController {
             target_pedal,
             delta_accel,
             pedal_delta,
+            instability_score,
+            unstable: instability.is_unstable(),
+            descent_hand_brake,
+            authority_utilization,
+            wheel_torque: physics.mass() * setpoint_accel * physics.wheel_radius(),
+            input_stale: measurement.is_stale(),
+            slip_ratio: None,
+            steering_saturated: steer_controller.saturated,
+            estimated_grade_radians,
+            first_step: is_first_step,
+            decel_authority_exceeded,
+            steady_hold: is_steady_holding,
+            steering_tracking_error,
         };
 
+        if let Some(verbose_report) = verbose_report {
+            let model_accel = match resistive_model {
+                Some(model) => model.acceleration(measurement.speed, pitch_radians, reverse),
+                None => physics.driving_impedance_acceleration(
+                    measurement.speed,
+                    pitch_radians,
+                    reverse,
+                ),
+            };
+            *verbose_report = VerboseReport {
+                measured_speed: measurement.speed,
+                measured_accel: measurement.accel,
+                pitch_radians,
+                throttle_lower_border,
+                brake_upper_border,
+                speed_pid: speed_pid_terms,
+                accel_pid: accel_pid_terms,
+                resistive_force: model_accel * physics.mass(),
+                commanded_force: setpoint_accel * physics.mass(),
+                steering_saturated: report.steering_saturated,
+                unstable: report.unstable,
+            };
+        }
+
         (output, report)
     }
+
+    /// Feeds a recorded sequence of inputs to the controller and
+    /// collects the outputs of every step.
+    ///
+    /// Each [ReplayStep] sets the target and then runs
+    /// [step](VehicleController::step). It is intended for offline
+    /// regression testing with a freshly built controller.
+    pub fn replay(
+        &mut self,
+        inputs: impl IntoIterator<Item = ReplayStep>,
+    ) -> Vec<(Output, Report)> {
+        inputs
+            .into_iter()
+            .map(|input| {
+                let ReplayStep {
+                    time_delta_sec,
+                    current_speed,
+                    pitch_radians,
+                    target,
+                } = input;
+                self.set_target(target);
+                self.step(time_delta_sec, current_speed, pitch_radians)
+            })
+            .collect()
+    }
+
+    /// Simulates `num_steps` steps on a clone of the controller,
+    /// leaving this controller untouched, e.g. for short-horizon
+    /// planning.
+    ///
+    /// The `plant` applies each output for `time_delta_sec` seconds
+    /// and returns the resulting speed, which feeds the next step
+    /// starting from `initial_speed`. The current targets are kept.
+    pub fn rollout<F>(
+        &self,
+        num_steps: usize,
+        time_delta_sec: f64,
+        initial_speed: f64,
+        pitch_radians: f64,
+        mut plant: F,
+    ) -> Vec<(Output, Report)>
+    where
+        F: FnMut(&Output, f64) -> f64,
+    {
+        let mut controller = self.clone();
+        let mut speed = initial_speed;
+        (0..num_steps)
+            .map(|_| {
+                let step = controller.step(time_delta_sec, speed, pitch_radians);
+                speed = plant(&step.0, time_delta_sec);
+                step
+            })
+            .collect()
+    }
+
+    /// Replays explicit inputs on a clone of the controller like
+    /// [replay](VehicleController::replay), leaving this controller
+    /// untouched.
+    pub fn rollout_replay(
+        &self,
+        inputs: impl IntoIterator<Item = ReplayStep>,
+    ) -> Vec<(Output, Report)> {
+        self.clone().replay(inputs)
+    }
+}
+
+/// Computes `(throttle_lower_border, brake_upper_border)` with the
+/// custom resistive model if present, shifted by the
+/// `(throttle, brake)` border offsets.
+fn pedal_borders(
+    physics: &VehiclePhysics,
+    resistive_model: Option<&ResistiveModel>,
+    (throttle_offset, brake_offset): (f64, f64),
+    speed: f64,
+    pitch_radians: f64,
+    reverse: bool,
+) -> (f64, f64) {
+    let (throttle_lower_border, brake_upper_border) = match resistive_model {
+        Some(model) => {
+            let throttle_lower_border = model.acceleration(speed, pitch_radians, reverse);
+            let brake_upper_border = throttle_lower_border + physics.lay_off_engine_acceleration();
+            (throttle_lower_border, brake_upper_border)
+        }
+        None => physics.pedal_borders(speed, pitch_radians, reverse),
+    };
+    (
+        throttle_lower_border + throttle_offset,
+        brake_upper_border + brake_offset,
+    )
+}
+
+/// Computes the brake range in the pedal domain, widened near
+/// standstill if a `(low_speed_max_decel, low_speed_decel_speed)` is
+/// configured.
+fn max_brake_pedal(
+    max_pedal: f64,
+    max_decel: f64,
+    (low_speed_max_decel, low_speed_decel_speed): (Option<f64>, f64),
+    speed: f64,
+) -> f64 {
+    match low_speed_max_decel {
+        Some(low_speed_max_decel) if low_speed_decel_speed > 0.0 => {
+            let weight = (1.0 - speed.abs() / low_speed_decel_speed).clamp(0.0, 1.0);
+            let decel = max_decel + weight * (low_speed_max_decel - max_decel);
+            max_pedal * decel / max_decel
+        }
+        _ => max_pedal,
+    }
+}
+
+/// Limits the setpoint accel to the grip budget left by the lateral
+/// accel, which never limits braking, and then to the
+/// `(comfort_accel_limit, comfort_decel_limit)` except in a full stop.
+fn limit_setpoint_accel(
+    setpoint_accel: f64,
+    full_stop: bool,
+    grip_budget: Option<f64>,
+    (comfort_accel_limit, comfort_decel_limit): (Option<f64>, Option<f64>),
+) -> f64 {
+    let setpoint_accel = grip_budget.map_or(setpoint_accel, |budget| setpoint_accel.min(budget));
+    if full_stop {
+        return setpoint_accel;
+    }
+    let setpoint_accel =
+        comfort_accel_limit.map_or(setpoint_accel, |limit| setpoint_accel.min(limit));
+    comfort_decel_limit.map_or(setpoint_accel, |limit| setpoint_accel.max(-limit))
+}
+
+/// Interpolates the speed at `time_sec` in a non-empty profile sorted
+/// by time. It also serves the [PedalCurve::Lookup] tables.
+fn interpolate_profile(profile: &[(f64, f64)], time_sec: f64) -> f64 {
+    let index = profile.partition_point(|&(time, _)| time <= time_sec);
+    match (
+        index.checked_sub(1).map(|prev| profile[prev]),
+        profile.get(index),
+    ) {
+        (Some((t0, v0)), Some(&(t1, v1))) => v0 + (v1 - v0) * (time_sec - t0) / (t1 - t0),
+        (Some((_, speed)), None) | (None, Some(&(_, speed))) => speed,
+        (None, None) => unreachable!(),
+    }
+}
+
+/// Rounds `value` to the nearest multiple of `step` if it is set and
+/// positive.
+fn quantize(value: f64, step: Option<f64>) -> f64 {
+    match step {
+        Some(step) if step > 0.0 => (value / step).round() * step,
+        _ => value,
+    }
+}
+
+/// Computes the weight of a regime in [0, 1] given the distance of the
+/// target pedal into the regime past its border, ramping linearly over
+/// a zone of `width` centered at the border.
+fn blend_weight(distance: f64, width: f64) -> f64 {
+    ((distance + width / 2.0) / width).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::STAND_STILL_SPEED_MS;
+
+    const TIME_DELTA_SEC: f64 = 0.05;
+
+    fn sedan() -> VehicleControllerInit {
+        VehicleControllerInit::from_physics(VehiclePhysics::preset(VehicleClass::Sedan), None)
+    }
+
+    fn target(speed: f64) -> TargetRequest {
+        TargetRequest {
+            steering_angle: 0.0,
+            speed,
+            accel: 0.0,
+        }
+    }
+
+    /// A point mass on flat ground with the signed velocity `velocity`.
+    /// The throttle drives in the direction of the gear, and the brake
+    /// and the resistance slow the vehicle down without reversing it.
+    fn plant_step(physics: &VehiclePhysics, output: &Output, velocity: f64) -> f64 {
+        let direction = if output.reverse { -1.0 } else { 1.0 };
+        let drive = direction * output.throttle * physics.max_accel();
+        let resistance = physics
+            .driving_impedance_acceleration(velocity.abs(), 0.0, false)
+            .abs();
+        let decel = output.brake * physics.max_deceleration() + resistance;
+
+        let velocity = velocity + drive * TIME_DELTA_SEC;
+        let decel_delta = decel * TIME_DELTA_SEC;
+        if velocity > decel_delta {
+            velocity - decel_delta
+        } else if velocity < -decel_delta {
+            velocity + decel_delta
+        } else {
+            0.0
+        }
+    }
+
+    /// Drives the controller on [plant_step] starting at `velocity` and
+    /// returns the signed velocities with the outputs and reports.
+    fn simulate(
+        controller: &mut VehicleController,
+        mut velocity: f64,
+        num_steps: usize,
+    ) -> Vec<(f64, Output, Report)> {
+        let physics = controller.physics().clone();
+        (0..num_steps)
+            .map(|_| {
+                let (output, report) = controller.step(TIME_DELTA_SEC, velocity.abs(), 0.0);
+                velocity = plant_step(&physics, &output, velocity);
+                (velocity, output, report)
+            })
+            .collect()
+    }
+
+    /// Drives through the target speeds `speeds` scaled by `sign`,
+    /// 30 seconds each, starting from rest.
+    fn drive_sequence(sign: f64, speeds: &[f64]) -> Vec<(f64, Output, Report)> {
+        let mut controller = sedan().build();
+        let mut velocity = 0.0;
+        let mut trace = vec![];
+        for &speed in speeds {
+            controller.set_target(target(sign * speed));
+            let steps = simulate(&mut controller, velocity, 600);
+            velocity = steps.last().unwrap().0;
+            trace.extend(steps);
+        }
+        trace
+    }
+
+    #[test]
+    fn reverse_mirrors_forward() {
+        let speeds = [8.0, 2.0, 5.0, 0.0];
+        let forward = drive_sequence(1.0, &speeds);
+        let backward = drive_sequence(-1.0, &speeds);
+
+        for ((fwd_velocity, fwd_output, fwd_report), (bwd_velocity, bwd_output, bwd_report)) in
+            forward.iter().zip(&backward)
+        {
+            assert!((fwd_velocity + bwd_velocity).abs() < 1e-9);
+            assert!((fwd_output.throttle - bwd_output.throttle).abs() < 1e-9);
+            assert!((fwd_output.brake - bwd_output.brake).abs() < 1e-9);
+            assert_eq!(fwd_report.status, bwd_report.status);
+            assert!(!fwd_output.reverse);
+            if bwd_report.status != Status::FullStop {
+                assert!(bwd_output.reverse);
+            }
+        }
+
+        // The sequence passes every pedal regime in reverse
+        for status in [Status::Accelerating, Status::Coasting, Status::Braking] {
+            assert!(backward
+                .iter()
+                .any(|(_, output, report)| output.reverse && report.status == status));
+        }
+    }
+
+    #[test]
+    fn reverse_target_decelerates_through_zero() {
+        let mut controller = sedan().build();
+        controller.set_target(target(8.0));
+        simulate(&mut controller, 8.0, 10);
+
+        controller.set_target(target(-3.0));
+        let trace = simulate(&mut controller, 8.0, 600);
+
+        // The gear changes only after the vehicle stopped
+        let mut velocity = 8.0;
+        for (next_velocity, output, _) in &trace {
+            if output.reverse {
+                assert!(velocity < STAND_STILL_SPEED_MS);
+            }
+            velocity = *next_velocity;
+        }
+
+        let stop_step = trace.iter().position(|&(velocity, _, _)| velocity == 0.0);
+        assert!(stop_step.unwrap() < 200);
+        assert!(trace.last().unwrap().0 < -0.5);
+    }
+
+    #[test]
+    fn grip_budget_caps_only_acceleration() {
+        let mut init = sedan();
+        init.friction_coefficient = Some(0.8);
+        let mut controller = init.clone().build();
+        controller.set_lateral_accel(9.0);
+        assert_eq!(controller.longitudinal_grip_budget(), Some(0.0));
+
+        // Braking from a corner at the grip limit
+        controller.set_target(target(0.0));
+        let trace = simulate(&mut controller, 20.0, 20);
+        let (_, output, report) = trace.last().unwrap();
+        assert!(report.setpoint_accel < 0.0);
+        assert!(output.brake > 0.0);
+        assert_eq!(output.throttle, 0.0);
+
+        // Accelerating out of a corner
+        let mut controller = init.build();
+        controller.set_lateral_accel(7.5);
+        let budget = controller.longitudinal_grip_budget().unwrap();
+        controller.set_target(TargetRequest {
+            steering_angle: 0.0,
+            speed: 30.0,
+            accel: 3.0,
+        });
+        for (_, _, report) in simulate(&mut controller, 10.0, 100) {
+            assert!(report.setpoint_accel <= budget + 1e-9);
+        }
+        let (min_accel, max_accel) = controller.effective_accel_limits(10.0, 0.0);
+        assert!(max_accel <= budget + 1e-9);
+        assert!(min_accel < -budget);
+    }
+
+    /// Accelerates from rest toward 10 m/s in steps of `time_delta_sec`
+    /// for 30 seconds, integrating the plant finely, and returns the
+    /// final speed.
+    fn speed_after_coarse_steps(max_substep_sec: Option<f64>, time_delta_sec: f64) -> f64 {
+        let mut init = sedan();
+        init.max_substep_sec = max_substep_sec;
+        let mut controller = init.build();
+        controller.set_target(target(10.0));
+        let physics = controller.physics().clone();
+
+        let plant_steps = (time_delta_sec / TIME_DELTA_SEC).round() as usize;
+        let mut velocity = 0.0;
+        for _ in 0..(30.0 / time_delta_sec) as usize {
+            let (output, _) = controller.step(time_delta_sec, velocity, 0.0);
+            for _ in 0..plant_steps {
+                velocity = plant_step(&physics, &output, velocity);
+            }
+        }
+        velocity
+    }
+
+    #[test]
+    fn substeps_converge_like_fine_steps() {
+        let fine = speed_after_coarse_steps(None, TIME_DELTA_SEC);
+        let single = speed_after_coarse_steps(None, 0.5);
+        let substepped = speed_after_coarse_steps(Some(TIME_DELTA_SEC), 0.5);
+        assert!((substepped - fine).abs() < (single - fine).abs());
+        assert!((substepped - fine).abs() < 0.5);
+    }
+
+    #[test]
+    fn substeps_keep_per_sample_state() {
+        let mut init = sedan();
+        init.max_substep_sec = Some(0.01);
+        init.stale_speed_samples = Some(5);
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+
+        let (_, report) = controller.step(0.2, 5.0, 0.0);
+        assert!(report.first_step);
+        assert!(!report.input_stale);
+
+        // The fifth sample of the same speed is stale
+        for _ in 0..3 {
+            let (_, report) = controller.step(0.2, 5.0, 0.0);
+            assert!(!report.first_step);
+            assert!(!report.input_stale);
+        }
+        let (_, report) = controller.step(0.2, 5.0, 0.0);
+        assert!(report.input_stale);
+    }
+
+    #[test]
+    fn full_stop_hold_is_not_jerk_limited() {
+        let mut init = sedan();
+        init.max_pedal_jerk = Some(1.0);
+        init.hand_brake_dwell_sec = 1.0;
+        let mut controller = init.build();
+        controller.set_target(target(0.0));
+
+        let trace = simulate(&mut controller, 0.0, 40);
+        for (index, (_, output, report)) in trace.iter().enumerate() {
+            assert_eq!(report.status, Status::FullStop);
+            assert_eq!(output.brake, 1.0);
+            assert_eq!(output.hand_brake, index >= 19);
+        }
+
+        // The brake is released within the jerk limit
+        controller.set_target(target(5.0));
+        let (output, _) = controller.step(TIME_DELTA_SEC, 0.0, 0.0);
+        assert!(output.brake < 1.0 && output.brake > 0.99);
+    }
+
+    #[test]
+    fn pedal_blend_without_coasting_gap_still_brakes() {
+        let mut init = sedan();
+        init.pedal_blend_width = 0.2;
+        init.brake_border_offset = 0.5;
+        init.speed_controller.accel_mode = AccelMode::Setpoint;
+        let mut controller = init.build();
+        let (throttle_lower_border, brake_upper_border) =
+            controller.physics().pedal_borders(10.0, 0.0, false);
+        assert!(throttle_lower_border <= brake_upper_border + 0.5);
+
+        controller.set_target(TargetRequest {
+            steering_angle: 0.0,
+            speed: 5.0,
+            accel: -0.48,
+        });
+        let trace = simulate(&mut controller, 10.0, 100);
+        for (_, output, report) in trace.iter().skip(1) {
+            if report.target_pedal < throttle_lower_border - 0.1 {
+                assert_eq!(report.status, Status::Braking);
+                assert!(output.brake > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn target_pedal_for_throttle_includes_border_offsets() {
+        let mut init = sedan();
+        init.throttle_border_offset = 0.3;
+        let target_pedal = init.target_pedal_for_throttle(0.5, 10.0, 0.0);
+        let controller = init.build();
+        assert!((controller.pedal_for_accel(target_pedal, 10.0, 0.0) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pedal_for_accel_uses_low_speed_brake_range() {
+        let mut init = sedan();
+        init.low_speed_max_decel = Some(12.0);
+        init.low_speed_decel_speed = 5.0;
+        let controller = init.build();
+        let (_, brake_upper_border) = controller.physics().pedal_borders(1.0, 0.0, false);
+
+        // At 1 m/s the brake range widens from 3 to 3 * 11.2 / 8 = 4.2
+        let pedal = controller.pedal_for_accel(brake_upper_border - 3.5, 1.0, 0.0);
+        let brake = controller.accel_controller.brake_command(3.5 / 4.2);
+        assert!((pedal + brake).abs() < 1e-9);
+        assert!(pedal > -1.0);
+    }
+
+    #[test]
+    fn replay_is_deterministic() {
+        let inputs: Vec<_> = (0..200)
+            .map(|index| {
+                let time_sec = index as f64 * TIME_DELTA_SEC;
+                ReplayStep {
+                    time_delta_sec: TIME_DELTA_SEC,
+                    current_speed: 6.0 * (0.3 * time_sec).sin(),
+                    pitch_radians: 0.05 * (0.7 * time_sec).cos(),
+                    target: TargetRequest {
+                        steering_angle: 0.2 * (0.5 * time_sec).sin(),
+                        speed: if index < 120 { 8.0 } else { -2.0 },
+                        accel: 1.5,
+                    },
+                }
+            })
+            .collect();
+
+        let first = sedan().build().replay(inputs.clone());
+        let second = sedan().build().replay(inputs);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn output_validation_rejects_invalid_values() {
+        let output = Output {
+            throttle: 0.5,
+            brake: 0.0,
+            steer: 0.0,
+            reverse: false,
+            hand_brake: false,
+            brake_bias: None,
+        };
+        assert_eq!(output.validate(), Ok(()));
+        let invalid = |throttle, brake, steer| Output {
+            throttle,
+            brake,
+            steer,
+            ..output.clone()
+        };
+        assert_eq!(
+            invalid(f64::NAN, 0.0, 0.0).validate(),
+            Err(OutputError::NotFinite)
+        );
+        assert_eq!(
+            invalid(1.5, 0.0, 0.0).validate(),
+            Err(OutputError::ThrottleOutOfRange(1.5))
+        );
+        assert_eq!(
+            invalid(0.0, -0.1, 0.0).validate(),
+            Err(OutputError::BrakeOutOfRange(-0.1))
+        );
+        assert_eq!(
+            invalid(0.0, 0.0, 2.0).validate(),
+            Err(OutputError::SteerOutOfRange(2.0))
+        );
+        assert_eq!(
+            invalid(0.2, 0.1, 0.0).validate(),
+            Err(OutputError::ThrottleAndBrake {
+                throttle: 0.2,
+                brake: 0.1
+            })
+        );
+        assert_eq!(invalid(0.2, 0.1, 0.0).validate_with_overlap(0.1), Ok(()));
+    }
+
+    #[test]
+    fn try_step_rejects_corrupted_state() {
+        let mut init = sedan();
+        init.validate_output = true;
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+        assert!(controller.try_step(TIME_DELTA_SEC, 0.0, 0.0).is_ok());
+
+        // A zero steering limit turns the steer ratio into NaN
+        controller.steer_controller.max_right_steering_angle = 0.0;
+        let last_output = controller.last_output().cloned();
+        assert_eq!(
+            controller.try_step(TIME_DELTA_SEC, 0.0, 0.0),
+            Err(OutputError::NotFinite)
+        );
+        assert_eq!(controller.last_output().cloned(), last_output);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "invalid controller output")]
+    fn step_asserts_valid_output_in_debug_builds() {
+        let mut controller = sedan().build();
+        controller.steer_controller.max_right_steering_angle = 0.0;
+        controller.step(TIME_DELTA_SEC, 0.0, 0.0);
+    }
+
+    #[test]
+    fn creep_hold_brakes_against_creep_at_full_stop() {
+        let mut init = sedan();
+        init.creep_accel = Some(0.5);
+        init.hand_brake_dwell_sec = 1.0;
+        init.max_normal_brake = 0.1;
+        let mut controller = init.build();
+        controller.set_target(target(0.0));
+
+        let hold_brake = 0.5 / controller.accel_controller.max_pedal();
+        for index in 0..40 {
+            let (output, report) = controller.step(TIME_DELTA_SEC, 0.0, 0.0);
+            assert_eq!(report.status, Status::FullStop);
+            assert!((output.brake - hold_brake).abs() < 1e-9);
+            assert_eq!(output.hand_brake, index >= 19);
+        }
+    }
+
+    #[test]
+    fn emergency_stop_bypasses_normal_brake_cap() {
+        let mut init = sedan();
+        init.max_normal_brake = 0.5;
+
+        let mut controller = init.clone().build();
+        controller.set_target(target(20.0));
+        simulate(&mut controller, 20.0, 10);
+        controller.set_target(TargetRequest {
+            steering_angle: 0.0,
+            speed: 0.0,
+            accel: -8.0,
+        });
+        let trace = simulate(&mut controller, 20.0, 400);
+        let moving = trace
+            .iter()
+            .filter(|(_, _, report)| report.status != Status::FullStop);
+        assert!(moving.clone().all(|(_, output, _)| output.brake <= 0.5));
+        assert!(moving.clone().any(|(_, output, _)| output.brake == 0.5));
+
+        let mut controller = init.build();
+        controller.set_target(target(20.0));
+        simulate(&mut controller, 20.0, 10);
+        controller.emergency_stop();
+        let trace = simulate(&mut controller, 20.0, 400);
+        let (_, output, report) = &trace[0];
+        assert_eq!(report.status, Status::Braking);
+        assert_eq!(output.brake, 1.0);
+        assert_eq!(trace.last().unwrap().0, 0.0);
+        assert_eq!(trace.last().unwrap().2.status, Status::FullStop);
+    }
+
+    #[test]
+    fn accel_limits_follow_brake_cap_and_launch_limit() {
+        let uncapped = sedan().build();
+        let (uncapped_min, _) = uncapped.effective_accel_limits(10.0, 0.0);
+
+        let mut init = sedan();
+        init.max_normal_brake = 0.3;
+        init.speed_controller.launch_accel = Some(0.5);
+        let controller = init.build();
+        let (min_accel, _) = controller.effective_accel_limits(10.0, 0.0);
+        assert!(min_accel > uncapped_min);
+        // The floor is reached at the capped brake
+        assert!((controller.pedal_for_accel(min_accel, 10.0, 0.0) + 0.3).abs() < 1e-9);
+
+        let launch_speed = controller.speed_controller.launch_speed();
+        let (_, launch_max) = controller.effective_accel_limits(launch_speed / 2.0, 0.0);
+        let (_, cruise_max) = controller.effective_accel_limits(launch_speed * 2.0, 0.0);
+        assert_eq!(launch_max, 0.5);
+        assert!(cruise_max > 0.5);
+    }
+
+    #[test]
+    fn step_accel_follows_ramped_target() {
+        let mut init = sedan();
+        init.target_speed_ramp_rate = Some(1.0);
+        let mut controller = init.build();
+        controller.set_target(target(10.0));
+
+        let setpoints: Vec<f64> = (0..20)
+            .map(|_| controller.step_accel(TIME_DELTA_SEC, 0.0, 0.0))
+            .collect();
+        assert!(setpoints.iter().all(|&accel| accel >= 0.0));
+        assert!(*setpoints.last().unwrap() > 0.0);
+        assert!(controller.speed_controller.target_speed() > 0.0);
+    }
+
+    #[test]
+    fn speed_ramp_starts_from_measured_speed() {
+        let mut init = sedan();
+        init.target_speed_ramp_rate = Some(1.0);
+        let mut controller = init.build();
+        controller.set_target(target(20.0));
+
+        let trace = simulate(&mut controller, 20.0, 100);
+        // The first step brakes on the accel spike from zero speed
+        assert!(trace
+            .iter()
+            .filter(|(_, _, report)| !report.first_step)
+            .all(|(_, output, report)| report.status != Status::Braking && output.brake == 0.0));
+        assert!((controller.speed_controller.target_speed() - 20.0).abs() < 1e-9);
+        assert!((trace.last().unwrap().0 - 20.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn status_codes_round_trip() {
+        for status in [
+            Status::FullStop,
+            Status::Accelerating,
+            Status::Coasting,
+            Status::Braking,
+        ] {
+            assert_eq!(Status::from_code(status.as_code()), Some(status));
+        }
+        assert_eq!(Status::from_code(4), None);
+    }
+
+    #[test]
+    fn non_finite_targets_keep_previous_target() {
+        let mut controller = sedan().build();
+        controller.set_target(TargetRequest {
+            steering_angle: 0.1,
+            speed: 5.0,
+            accel: 1.0,
+        });
+        controller.set_target(TargetRequest {
+            steering_angle: f64::NAN,
+            speed: f64::INFINITY,
+            accel: f64::NAN,
+        });
+        assert_eq!(controller.speed_controller.target_speed(), 5.0);
+        assert_eq!(controller.requested_accel, 1.0);
+        assert_eq!(controller.steer_controller.target_steering_angle, 0.1);
+    }
+
+    #[test]
+    fn disallowed_reverse_stops_instead() {
+        let mut init = sedan();
+        init.allow_reverse = false;
+        let mut controller = init.build();
+        controller.set_target(target(-3.0));
+        for (velocity, output, _) in simulate(&mut controller, 0.0, 200) {
+            assert!(!output.reverse);
+            assert!(velocity >= 0.0);
+        }
+    }
+
+    #[test]
+    fn gear_changes_respect_min_reverse_interval() {
+        let mut init = sedan();
+        init.min_reverse_interval_sec = Some(5.0);
+        let mut controller = init.build();
+        controller.set_target(target(-2.0));
+        let mut trace = simulate(&mut controller, 0.0, 40);
+        controller.set_target(target(2.0));
+        let velocity = trace.last().unwrap().0;
+        trace.extend(simulate(&mut controller, velocity, 200));
+
+        let reverse_index = trace.iter().position(|(_, output, _)| output.reverse);
+        let reverse_index = reverse_index.expect("reverse is never engaged");
+        let forward_index = trace[reverse_index..]
+            .iter()
+            .position(|(_, output, _)| !output.reverse)
+            .expect("forward is never engaged again");
+        assert!(forward_index as f64 * TIME_DELTA_SEC >= 5.0 - 1e-9);
+    }
+
+    #[test]
+    fn neutral_coasts_without_pedals() {
+        let mut controller = sedan().build();
+        controller.set_neutral(true);
+        controller.set_target(target(10.0));
+        for (_, output, _) in simulate(&mut controller, 5.0, 20) {
+            assert_eq!((output.throttle, output.brake), (0.0, 0.0));
+            assert!(!output.hand_brake);
+        }
+        assert!(controller.is_neutral());
+    }
+
+    #[test]
+    fn stop_and_hold_bounds_decel_and_holds() {
+        let mut controller = sedan().build();
+        controller.set_target(target(5.0));
+        simulate(&mut controller, 5.0, 10);
+        controller.stop_and_hold(2.0);
+        let trace = simulate(&mut controller, 5.0, 200);
+        for (_, _, report) in &trace {
+            assert!(report.setpoint_accel >= -2.0 - 1e-9);
+        }
+        let (velocity, output, report) = trace.last().unwrap();
+        assert_eq!(*velocity, 0.0);
+        assert_eq!(report.status, Status::FullStop);
+        assert!(output.brake > 0.0);
+    }
+
+    #[test]
+    fn brake_at_tracks_decel_in_limit_mode() {
+        let mut controller = sedan().build();
+        controller.set_target(target(10.0));
+        simulate(&mut controller, 10.0, 10);
+        controller.brake_at(2.0);
+        let (_, report) = controller.step(TIME_DELTA_SEC, 10.0, 0.0);
+        assert_eq!(report.setpoint_accel, -2.0);
+
+        controller.set_target(target(10.0));
+        assert!(!controller.speed_controller.is_tracking_decel());
+    }
+
+    #[test]
+    fn speed_profile_overrides_target_speed() {
+        let mut controller = sedan().build();
+        controller.set_target(target(2.0));
+        controller.set_speed_profile(vec![(1.0, 10.0), (0.0, 0.0)]);
+        controller.step(0.5, 0.0, 0.0);
+        assert!((controller.speed_controller.target_speed() - 5.0).abs() < 1e-9);
+        controller.step(1.0, 0.0, 0.0);
+        assert_eq!(controller.speed_controller.target_speed(), 10.0);
+    }
+
+    #[test]
+    fn repeated_speed_is_flagged_stale() {
+        let mut init = sedan();
+        init.stale_speed_samples = Some(3);
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+        let stale: Vec<_> = [5.0, 5.0, 5.0, 5.1, 0.0, 0.0, 0.0]
+            .into_iter()
+            .map(|speed| controller.step(TIME_DELTA_SEC, speed, 0.0).1.input_stale)
+            .collect();
+        assert_eq!(stale, [false, false, true, false, false, false, false]);
+
+        // Fewer than two samples cannot repeat
+        for samples in [0, 1] {
+            let mut init = sedan();
+            init.stale_speed_samples = Some(samples);
+            let mut controller = init.build();
+            controller.set_target(target(5.0));
+            for speed in [5.0, 5.0, 5.1] {
+                assert!(!controller.step(TIME_DELTA_SEC, speed, 0.0).1.input_stale);
+            }
+        }
+    }
+
+    #[test]
+    fn steer_ratio_limit_caps_steer() {
+        let mut init = sedan();
+        init.steer_ratio_limit = 0.5;
+        let max_steering_angle = init.max_steering_angle;
+        let mut controller = init.build();
+
+        controller.set_target(TargetRequest {
+            steering_angle: -max_steering_angle,
+            speed: 5.0,
+            accel: 0.0,
+        });
+        let (output, _) = controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert_eq!(output.steer, -0.5);
+
+        controller.set_target(TargetRequest {
+            steering_angle: 0.1 * max_steering_angle,
+            speed: 5.0,
+            accel: 0.0,
+        });
+        let (output, _) = controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert!((output.steer - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn left_positive_convention_flips_steer() {
+        let mut init = sedan();
+        init.steer_convention = SteerConvention::LeftPositive;
+        let max_steering_angle = init.max_steering_angle;
+        let mut controller = init.build();
+
+        controller.set_target(TargetRequest {
+            steering_angle: 0.1 * max_steering_angle,
+            speed: 5.0,
+            accel: 0.0,
+        });
+        let (output, _) = controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert!((output.steer + 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn steering_ramp_reports_tracking_error() {
+        let mut init = sedan();
+        init.steering_ramp_rate = Some(1.0);
+        let mut controller = init.build();
+        controller.set_target(TargetRequest {
+            steering_angle: 0.5,
+            speed: 5.0,
+            accel: 0.0,
+        });
+        assert_eq!(controller.steering_tracking_error(), 0.5);
+        let (_, report) = controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert!((report.steering_tracking_error - 0.45).abs() < 1e-9);
+        simulate(&mut controller, 5.0, 20);
+        assert_eq!(controller.steering_tracking_error(), 0.0);
+    }
+
+    #[test]
+    fn startup_blanking_holds_accel_pid() {
+        let mut init = sedan();
+        init.startup_blanking_steps = 3;
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+        let trace = simulate(&mut controller, 0.0, 5);
+        for (_, _, report) in &trace[..3] {
+            assert_eq!(report.pedal_delta, 0.0);
+        }
+        assert!(trace[3..]
+            .iter()
+            .any(|(_, _, report)| report.pedal_delta != 0.0));
+    }
+
+    #[test]
+    fn outputs_round_to_f32() {
+        let mut init = sedan();
+        init.round_output_to_f32 = true;
+        let mut controller = init.build();
+        controller.set_target(TargetRequest {
+            steering_angle: 0.123456789,
+            speed: 5.0,
+            accel: 0.0,
+        });
+        for (_, output, _) in simulate(&mut controller, 1.0, 50) {
+            assert_eq!(output, output.round_to_f32());
+        }
+    }
+
+    #[test]
+    fn last_step_is_kept_except_for_step_accel() {
+        let mut controller = sedan().build();
+        assert!(controller.last_output().is_none());
+        controller.set_target(target(5.0));
+        let (output, report) = controller.step(TIME_DELTA_SEC, 1.0, 0.0);
+        controller.step_accel(TIME_DELTA_SEC, 1.1, 0.0);
+        assert_eq!(controller.last_output(), Some(&output));
+        assert_eq!(controller.last_report(), Some(&report));
+    }
+
+    #[test]
+    fn comfort_limits_bound_setpoint_accel() {
+        let mut init = sedan();
+        init.speed_controller.accel_mode = AccelMode::Setpoint;
+        init.comfort_accel_limit = Some(1.0);
+        init.comfort_decel_limit = Some(1.5);
+        let mut controller = init.build();
+
+        controller.set_target(TargetRequest {
+            steering_angle: 0.0,
+            speed: 20.0,
+            accel: 3.0,
+        });
+        let (_, report) = controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert_eq!(report.setpoint_accel, 1.0);
+
+        controller.set_target(TargetRequest {
+            steering_angle: 0.0,
+            speed: 2.0,
+            accel: -4.0,
+        });
+        let (_, report) = controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert_eq!(report.setpoint_accel, -1.5);
+    }
+
+    #[test]
+    fn max_corner_speed_bounds_lateral_accel() {
+        assert_eq!(VehicleController::max_corner_speed(25.0, 4.0), 10.0);
+        assert_eq!(VehicleController::max_corner_speed(0.0, 4.0), 0.0);
+    }
+
+    #[test]
+    fn time_to_speed_uses_speed_limits() {
+        let mut controller = sedan().build();
+        assert_eq!(controller.time_to_speed(9.0), 3.0);
+        controller.set_target(target(8.0));
+        controller.step(TIME_DELTA_SEC, 8.0, 0.0);
+        assert_eq!(controller.time_to_speed(0.0), 1.0);
+    }
+
+    #[test]
+    fn peaks_are_tracked_until_reset() {
+        let mut controller = sedan().build();
+        controller.set_target(target(5.0));
+        simulate(&mut controller, 0.0, 100);
+
+        let peaks = controller.peaks();
+        assert!(peaks.max_speed > 0.0 && peaks.max_accel > 0.0);
+        controller.reset_peaks();
+        assert_eq!(controller.peaks(), Peaks::default());
+    }
+
+    #[test]
+    fn control_effort_is_tracked_until_reset() {
+        let mut init = sedan();
+        init.track_control_effort = true;
+        let mut controller = init.build();
+        assert_eq!(sedan().build().control_effort(), None);
+        controller.set_target(target(5.0));
+        simulate(&mut controller, 0.0, 100);
+
+        let effort = controller.control_effort().unwrap();
+        assert!((effort.duration_sec - 100.0 * TIME_DELTA_SEC).abs() < 1e-9);
+        assert!(effort.effort > 0.0);
+
+        controller.reset_control_effort();
+        assert_eq!(controller.control_effort(), Some(&ControlEffort::default()));
+    }
+
+    #[test]
+    fn reverse_flag_has_hysteresis() {
+        let mut init = sedan();
+        init.reverse_hysteresis = 0.5;
+        let mut controller = init.build();
+        let reverse: Vec<_> = [-0.3, -1.0, 0.3, 1.0]
+            .into_iter()
+            .map(|speed| {
+                controller.set_target(target(speed));
+                controller.step(TIME_DELTA_SEC, 0.0, 0.0).0.reverse
+            })
+            .collect();
+        assert_eq!(reverse, [false, true, true, false]);
+    }
+
+    #[test]
+    fn steering_at_standstill_is_optional() {
+        for steer_at_standstill in [false, true] {
+            let mut init = sedan();
+            init.steer_at_standstill = steer_at_standstill;
+            let mut controller = init.build();
+            controller.set_target(TargetRequest {
+                steering_angle: 0.3,
+                speed: 0.0,
+                accel: 0.0,
+            });
+            let (output, report) = controller.step(TIME_DELTA_SEC, 0.0, 0.0);
+            assert_eq!(report.status, Status::FullStop);
+            assert_eq!(output.steer != 0.0, steer_at_standstill);
+        }
+    }
+
+    #[test]
+    fn pedal_curves_shape_commands() {
+        assert_eq!(PedalCurve::Linear.apply(1.5), 1.0);
+        assert_eq!(PedalCurve::Quadratic.apply(0.5), 0.25);
+        assert_eq!(PedalCurve::Lookup(vec![]).apply(0.3), 0.3);
+        let curve = PedalCurve::Lookup(vec![(0.0, 0.0), (0.5, 0.2), (1.0, 1.0)]);
+        assert!((curve.apply(0.25) - 0.1).abs() < 1e-12);
+        assert!((curve.apply(0.75) - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pedal_curve_lookup_sorts_and_validates_table() {
+        let curve = PedalCurve::lookup(vec![(1.0, 1.0), (0.0, 0.0), (0.5, 0.2)]).unwrap();
+        assert_eq!(
+            curve,
+            PedalCurve::Lookup(vec![(0.0, 0.0), (0.5, 0.2), (1.0, 1.0)])
+        );
+        assert!((curve.apply(0.25) - 0.1).abs() < 1e-12);
+
+        assert_eq!(
+            PedalCurve::lookup(vec![(0.5, 0.2), (0.5, 0.3)]),
+            Err(PedalCurveError::RepeatedCommand(0.5))
+        );
+        assert!(matches!(
+            PedalCurve::lookup(vec![(0.0, 0.0), (f64::NAN, 0.5)]),
+            Err(PedalCurveError::NotFinite { .. })
+        ));
+    }
+
+    #[test]
+    fn yaw_correction_biases_brake() {
+        let mut init = sedan();
+        init.yaw_correction_gain = Some(2.0);
+        init.max_brake_bias = 0.3;
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+        controller.set_yaw_correction(0.1);
+        let (output, _) = controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert_eq!(output.brake_bias, Some(0.2));
+        controller.set_yaw_correction(-1.0);
+        let (output, _) = controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert_eq!(output.brake_bias, Some(-0.3));
+
+        let (output, _) = sedan().build().step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert_eq!(output.brake_bias, None);
+    }
+
+    #[test]
+    fn decel_authority_is_exceeded_on_steep_descent() {
+        let mut controller = sedan().build();
+        controller.set_target(target(10.0));
+        controller.step(TIME_DELTA_SEC, 10.0, 0.0);
+        controller.brake_at(2.0);
+        let (_, report) = controller.step(TIME_DELTA_SEC, 10.0, 0.0);
+        assert!(!report.decel_authority_exceeded);
+        let (_, report) = controller.step(TIME_DELTA_SEC, 10.0, 0.3);
+        assert!(report.decel_authority_exceeded);
+    }
+
+    #[test]
+    fn hand_brake_engages_when_brake_cannot_hold_descent() {
+        let mut init = sedan();
+        init.descent_hand_brake_threshold = Some(0.9);
+        let mut controller = init.build();
+        controller.set_target(target(10.0));
+        controller.step(TIME_DELTA_SEC, 10.0, 0.0);
+        controller.emergency_stop();
+
+        let reports: Vec<_> = [10.0, 10.1, 10.2]
+            .into_iter()
+            .map(|speed| controller.step(TIME_DELTA_SEC, speed, 0.3))
+            .collect();
+        let (output, report) = reports.last().unwrap();
+        assert_eq!(report.status, Status::Braking);
+        assert!(report.descent_hand_brake);
+        assert!(output.hand_brake);
+    }
+
+    #[test]
+    fn efficiency_weight_coasts_near_coasting_accel() {
+        let mut init = sedan();
+        init.efficiency_weight = 1.0;
+        init.speed_controller.accel_mode = AccelMode::Setpoint;
+        let mut controller = init.build();
+        let (throttle_lower_border, _) = controller.physics().pedal_borders(10.0, 0.0, false);
+
+        controller.set_target(TargetRequest {
+            steering_angle: 0.0,
+            speed: 20.0,
+            accel: throttle_lower_border + 0.1,
+        });
+        let (output, _) = controller.step(TIME_DELTA_SEC, 10.0, 0.0);
+        assert_eq!((output.throttle, output.brake), (0.0, 0.0));
+    }
+
+    #[test]
+    fn control_period_holds_output_between_cycles() {
+        let mut init = sedan();
+        init.control_period_sec = Some(0.2);
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+        let steps: Vec<_> = (0..8)
+            .map(|index| controller.step(TIME_DELTA_SEC, 0.1 * index as f64, 0.0))
+            .collect();
+        // The first step computes, and then every fourth one
+        for index in [1, 2, 3] {
+            assert_eq!(steps[index], steps[0]);
+        }
+        for index in [5, 6, 7] {
+            assert_eq!(steps[index], steps[4]);
+        }
+        assert_ne!(steps[4], steps[0]);
+    }
+
+    #[test]
+    fn measured_accel_can_include_gravity() {
+        let mut init = sedan();
+        init.measured_accel_includes_gravity = true;
+        init.verbose_report = true;
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+
+        let pitch_radians: f64 = 0.1;
+        let reading = 0.5 - gravity_compensated_accel(0.0, pitch_radians);
+        controller.step_with_measured_accel(TIME_DELTA_SEC, 5.0, reading, pitch_radians);
+        let report = controller.verbose_report().unwrap();
+        assert!((report.measured_accel - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn target_accel_is_quantized() {
+        let mut init = sedan();
+        init.target_accel_step = Some(0.5);
+        init.target_speed_step = Some(1.0);
+        let mut controller = init.build();
+        controller.set_target(TargetRequest {
+            steering_angle: 0.0,
+            speed: 10.4,
+            accel: 1.2,
+        });
+        assert_eq!(controller.requested_accel, 1.0);
+        assert_eq!(controller.speed_controller.target_speed(), 10.0);
+    }
+
+    #[test]
+    fn accel_estimate_is_bounded() {
+        let mut init = sedan();
+        init.accel_estimate_limit = 2.0;
+        init.verbose_report = true;
+        let mut controller = init.build();
+        controller.set_target(target(10.0));
+
+        controller.step(TIME_DELTA_SEC, 1.0, 0.0);
+        controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert_eq!(controller.verbose_report().unwrap().measured_accel, 2.0);
+    }
+
+    #[test]
+    fn pid_hook_receives_both_channels() {
+        use std::sync::{Arc, Mutex};
+
+        let channels = Arc::new(Mutex::new(vec![]));
+        let mut controller = sedan().build();
+        let sink = channels.clone();
+        controller.on_pid_output(move |channel, _| sink.lock().unwrap().push(channel));
+        controller.set_target(target(5.0));
+        controller.step(TIME_DELTA_SEC, 1.0, 0.0);
+        assert_eq!(
+            *channels.lock().unwrap(),
+            [PidChannel::Speed, PidChannel::Accel]
+        );
+
+        controller.clear_pid_output_hook();
+        controller.step(TIME_DELTA_SEC, 1.1, 0.0);
+        assert_eq!(channels.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rollout_leaves_controller_untouched() {
+        let mut controller = sedan().build();
+        controller.set_target(target(5.0));
+        let physics = controller.physics().clone();
+        let mut velocity = 0.0;
+        let trace = controller.rollout(20, TIME_DELTA_SEC, 0.0, 0.0, |output, _| {
+            velocity = plant_step(&physics, output, velocity);
+            velocity
+        });
+        assert_eq!(trace.len(), 20);
+        assert!(controller.last_output().is_none());
+        assert_eq!(controller.replay([]), vec![]);
+    }
+
+    #[test]
+    fn wheel_slip_scales_throttle() {
+        let mut init = sedan();
+        init.max_slip_ratio = Some(0.5);
+        let mut controller = init.build();
+        controller.set_target(target(10.0));
+        // Skip the first step, whose accel estimate brakes
+        controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        let mut reference = controller.clone();
+
+        // The wheels spin at twice the vehicle speed
+        let omega = 2.0 * 5.0 / controller.physics().wheel_radius();
+        let (output, report) =
+            controller.step_with_wheel_speeds(TIME_DELTA_SEC, 5.0, 0.0, &[omega]);
+        let (reference_output, _) = reference.step(TIME_DELTA_SEC, 5.0, 0.0);
+        assert!((report.slip_ratio.unwrap() - 1.0).abs() < 1e-9);
+        assert!(reference_output.throttle > 0.0);
+        assert!((output.throttle - 0.5 * reference_output.throttle).abs() < 1e-12);
+    }
+
+    #[test]
+    fn resistive_model_shifts_pedal_borders() {
+        let mut controller = sedan().build();
+        controller.set_resistive_model(|_, _, _| -1.0);
+        assert!((controller.pedal_for_accel(0.0, 10.0, 0.0) - 1.0 / 3.0).abs() < 1e-12);
+        assert!((controller.resistive_force(10.0, 0.0) + 1500.0).abs() < 1e-9);
+        controller.clear_resistive_model();
+        assert_eq!(
+            controller.cruise_pedal(10.0, 0.0),
+            controller.pedal_for_accel(0.0, 10.0, 0.0)
+        );
+    }
+
+    struct FixedSensors {
+        speed: f64,
+        pitch: f64,
+        accel: Option<f64>,
+    }
+
+    impl VehicleSensors for FixedSensors {
+        fn speed(&self) -> f64 {
+            self.speed
+        }
+
+        fn pitch(&self) -> f64 {
+            self.pitch
+        }
+
+        fn accel(&self) -> Option<f64> {
+            self.accel
+        }
+    }
+
+    #[test]
+    fn sensors_drive_the_matching_step() {
+        let mut controller = sedan().build();
+        controller.set_target(target(5.0));
+        let mut reference = controller.clone();
+
+        let sensors = FixedSensors {
+            speed: 2.0,
+            pitch: 0.05,
+            accel: None,
+        };
+        assert_eq!(
+            controller.step_from_sensors(&sensors, TIME_DELTA_SEC),
+            reference.step(TIME_DELTA_SEC, 2.0, 0.05)
+        );
+        let sensors = FixedSensors {
+            accel: Some(0.7),
+            ..sensors
+        };
+        assert_eq!(
+            controller.step_from_sensors(&sensors, TIME_DELTA_SEC),
+            reference.step_with_measured_accel(TIME_DELTA_SEC, 2.0, 0.7, 0.05)
+        );
+    }
+
+    #[test]
+    fn display_summarizes_output_and_report() {
+        let output = Output {
+            throttle: 0.5,
+            brake: 0.0,
+            steer: -0.25,
+            reverse: true,
+            hand_brake: false,
+            brake_bias: None,
+        };
+        assert_eq!(output.to_string(), "thr=0.50 brk=0.00 steer=-0.25 rev");
+        assert_eq!(Status::Coasting.to_string(), "Coasting");
+    }
+
+    #[test]
+    fn steady_hold_freezes_pedal_until_released() {
+        let mut init = sedan();
+        init.steady_hold_speed_error = Some(0.2);
+        init.steady_hold_accel_error = 0.5;
+        init.steady_hold_dwell_sec = 0.2;
+        init.steady_hold_release_speed_error = 1.0;
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+
+        let reports: Vec<_> = (0..10)
+            .map(|_| controller.step(TIME_DELTA_SEC, 5.0, 0.0).1)
+            .collect();
+        let report = reports.last().unwrap();
+        assert!(report.steady_hold);
+        assert_eq!(report.pedal_delta, 0.0);
+
+        let (_, report) = controller.step(TIME_DELTA_SEC, 3.5, 0.0);
+        assert!(!report.steady_hold);
+    }
+
+    #[test]
+    fn pitch_filter_smooths_pitch() {
+        let mut init = sedan();
+        init.pitch_filter_time_constant = Some(1.0);
+        init.verbose_report = true;
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+        controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        controller.step(TIME_DELTA_SEC, 5.0, 0.2);
+
+        let report = controller.verbose_report().unwrap();
+        assert!(report.pitch_radians > 0.0 && report.pitch_radians < 0.05);
+    }
+
+    #[test]
+    fn speed_filter_smooths_measured_speed() {
+        let mut init = sedan();
+        init.speed_filter_time_constant = Some(1.0);
+        init.verbose_report = true;
+        let mut controller = init.build();
+        controller.set_target(target(5.0));
+        controller.step(TIME_DELTA_SEC, 0.0, 0.0);
+        controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+
+        let report = controller.verbose_report().unwrap();
+        assert!(report.measured_speed > 0.0 && report.measured_speed < 1.0);
+    }
+
+    #[test]
+    fn oscillating_speed_is_flagged_unstable() {
+        let mut init = sedan();
+        init.instability_window = 4;
+        init.instability_threshold = 0.9;
+        let mut controller = init.build();
+        controller.set_target(target(5.5));
+        let reports: Vec<_> = [5.0, 6.0, 5.0, 6.0, 5.0, 6.0]
+            .into_iter()
+            .map(|speed| controller.step(TIME_DELTA_SEC, speed, 0.0).1)
+            .collect();
+        let report = reports.last().unwrap();
+        assert_eq!(report.instability_score, 1.0);
+        assert!(report.unstable);
+    }
+
+    #[test]
+    fn mass_changes_scale_commanded_force() {
+        let mut controller = sedan().build();
+        controller.set_target(target(10.0));
+        controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+        let force = controller.commanded_force();
+        assert_ne!(force, 0.0);
+
+        controller.set_mass(3000.0);
+        assert_eq!(controller.physics().mass(), 3000.0);
+        assert!((controller.commanded_force() - 2.0 * force).abs() < 1e-9);
+        assert!(
+            (controller.commanded_wheel_torque()
+                - controller.commanded_force() * controller.physics().wheel_radius())
+            .abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn step_accel_matches_step_setpoint_with_comfort_limits() {
+        let mut init = sedan();
+        init.speed_controller.accel_mode = AccelMode::Setpoint;
+        init.comfort_accel_limit = Some(1.0);
+        init.comfort_decel_limit = Some(1.5);
+        let mut stepped = init.clone().build();
+        let mut accel_stepped = init.build();
+
+        for request in [
+            TargetRequest {
+                steering_angle: 0.0,
+                speed: 20.0,
+                accel: 3.0,
+            },
+            TargetRequest {
+                steering_angle: 0.0,
+                speed: 2.0,
+                accel: -4.0,
+            },
+        ] {
+            stepped.set_target(request.clone());
+            accel_stepped.set_target(request);
+            for _ in 0..5 {
+                let (_, report) = stepped.step(TIME_DELTA_SEC, 5.0, 0.0);
+                let setpoint_accel = accel_stepped.step_accel(TIME_DELTA_SEC, 5.0, 0.0);
+                assert_eq!(setpoint_accel, report.setpoint_accel);
+                assert!((-1.5..=1.0).contains(&setpoint_accel));
+            }
+        }
+    }
+
+    #[test]
+    fn step_accel_respects_grip_budget() {
+        let mut init = sedan();
+        init.friction_coefficient = Some(0.8);
+        let mut controller = init.build();
+        controller.set_lateral_accel(7.82);
+        let budget = controller.longitudinal_grip_budget().unwrap();
+        assert!(budget > 0.0);
+
+        controller.set_target(target(20.0));
+        for _ in 0..20 {
+            let setpoint_accel = controller.step_accel(TIME_DELTA_SEC, 5.0, 0.0);
+            assert!(setpoint_accel <= budget + 1e-9);
+        }
+        assert!((controller.step_accel(TIME_DELTA_SEC, 5.0, 0.0) - budget).abs() < 1e-9);
+    }
+
+    #[test]
+    fn step_accel_changes_gear_like_step() {
+        let mut init = sedan();
+        init.min_reverse_interval_sec = Some(1.0);
+        let mut stepped = init.clone().build();
+        let mut accel_stepped = init.build();
+
+        // Reverse from standstill, then forward again after the interval
+        for speed in [-3.0, 3.0] {
+            stepped.set_target(target(speed));
+            accel_stepped.set_target(target(speed));
+            for _ in 0..30 {
+                let (output, report) = stepped.step(TIME_DELTA_SEC, 0.0, 0.0);
+                let setpoint_accel = accel_stepped.step_accel(TIME_DELTA_SEC, 0.0, 0.0);
+                assert_eq!(setpoint_accel, report.setpoint_accel);
+                assert_eq!(accel_stepped.reverse, output.reverse);
+            }
+            assert_eq!(accel_stepped.reverse, speed < 0.0);
+        }
+
+        // The first step was taken by step_accel
+        let (_, report) = accel_stepped.step(TIME_DELTA_SEC, 0.0, 0.0);
+        assert!(!report.first_step);
+    }
+
+    #[test]
+    fn pegged_throttle_uses_full_authority() {
+        let mut controller = sedan().build();
+        controller.set_target(target(30.0));
+
+        // Climbing a steep grade saturates the throttle
+        let (output, report) = (0..20)
+            .map(|_| controller.step(TIME_DELTA_SEC, 5.0, -0.3))
+            .last()
+            .unwrap();
+        assert_eq!(output.throttle, 1.0);
+        assert_eq!(report.authority_utilization, 1.0);
+    }
+
+    #[test]
+    fn pid_integrals_are_clamped_under_persistent_error() {
+        let mut init = sedan();
+        init.speed_controller.pid.ki = 0.001;
+        init.speed_controller.pid.integral_limit = 0.2;
+        init.accel_controller.pid.ki = 0.005;
+        init.accel_controller.pid.integral_limit = 0.2;
+        let mut controller = init.build();
+        controller.set_target(target(20.0));
+
+        // The speed never follows the command
+        let mut speed_integrals = vec![];
+        let mut accel_integrals = vec![];
+        for _ in 0..200 {
+            controller.step(TIME_DELTA_SEC, 5.0, 0.0);
+            speed_integrals.push(controller.speed_pid_integral());
+            accel_integrals.push(controller.accel_pid_integral());
+        }
+        for integrals in [speed_integrals, accel_integrals] {
+            // The integral winds up over several steps and then stays
+            // at the limit
+            let is_clamped = |integral: &f64| (integral - 0.2).abs() < 1e-12;
+            let clamp_step = integrals.iter().position(is_clamped).unwrap();
+            assert!(clamp_step > 10);
+            assert!(integrals[clamp_step - 10] < integrals[clamp_step - 1]);
+            assert!(integrals[clamp_step..].iter().all(is_clamped));
+        }
+    }
+
+    #[test]
+    fn init_thresholds_reach_both_controllers() {
+        let mut init = sedan();
+        init.thresholds.stand_still_speed = 1.0;
+        let thresholds = init.thresholds;
+        let mut controller = init.build();
+        assert_eq!(controller.speed_controller.thresholds(), &thresholds);
+
+        // The gear changes below the raised standstill speed
+        controller.set_target(target(-3.0));
+        controller.step(TIME_DELTA_SEC, 0.5, 0.0);
+        assert!(controller.reverse);
+    }
 }